@@ -30,9 +30,9 @@ fn main() -> Result<()> {
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
         let mut answer = 0;
         let mut position = 50; // Starting position
-        for line in reader.lines() {
-            let direction  = line?;
-            let rotation = parse_rotation(&direction)?;
+        for line in parse::numbered_lines(reader) {
+            let (line_no, direction) = line?;
+            let rotation = parse_rotation(&direction, line_no)?;
             position = rotate(position, &rotation);
             if position == 0 {
                 answer += 1;
@@ -54,9 +54,9 @@ fn main() -> Result<()> {
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
         let mut answer = 0;
         let mut position = 50; // Starting position
-        for line in reader.lines() {
-            let direction  = line?;
-            let rotation = parse_rotation(&direction)?;
+        for line in parse::numbered_lines(reader) {
+            let (line_no, direction) = line?;
+            let rotation = parse_rotation(&direction, line_no)?;
             let (new_position, cnt_zero_crossed)  = rotate2(position, &rotation);
             answer += cnt_zero_crossed;
             position = new_position;
@@ -80,13 +80,13 @@ enum Rotation {
     Right(i32),
 }
 
-fn parse_rotation(s: &str) -> Result<Rotation> {
-    let (dir, delta_str) = s.split_at(1);
-    let delta = delta_str.parse()?;
-    match dir {
-        "L" => Ok(Rotation::Left(delta)),
-        "R" => Ok(Rotation::Right(delta)),
-        _ => Err(anyhow!("Invalid rotation direction: {}", dir)),
+fn parse_rotation(s: &str, line_no: usize) -> Result<Rotation> {
+    if let Some(delta) = s.strip_prefix('L') {
+        Ok(Rotation::Left(parse::parse_value(delta, line_no, 2)?))
+    } else if let Some(delta) = s.strip_prefix('R') {
+        Ok(Rotation::Right(parse::parse_value(delta, line_no, 2)?))
+    } else {
+        Err(parse::ParseError::new(line_no, 1, format!("invalid rotation '{}'", s)).into())
     }
 }
 
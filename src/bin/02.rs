@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use itertools::Itertools;
@@ -47,8 +47,11 @@ fn main() -> Result<()> {
 }
 
 fn solve<R: BufRead>(reader: R, max_factor_opt: Option<usize>) -> Result<usize> {
-    let input = reader.lines().next().unwrap()?;
-    let ranges = parse_input_ranges(&input);
+    let input = reader
+        .lines()
+        .next()
+        .ok_or_else(|| anyhow!("Empty input"))??;
+    let ranges = parse_input_ranges(&input)?;
 
     let invalid_numbers = generate_invalid_numbers(10, max_factor_opt);
 
@@ -60,16 +63,10 @@ fn solve<R: BufRead>(reader: R, max_factor_opt: Option<usize>) -> Result<usize>
         .sum())
 }
 
-fn parse_input_ranges(input: &str) -> Vec<(usize, usize)> {
-    input
-        .split(',')
-        .filter_map(|part| {
-            let bounds: Vec<&str> = part.split('-').collect();
-            let low = bounds.get(0)?.parse::<usize>().ok()?;
-            let high = bounds.get(1)?.parse::<usize>().ok()?;
-            Some((low, high))
-        })
-        .collect::<Vec<(usize, usize)>>()
+fn parse_input_ranges(input: &str) -> Result<Vec<(usize, usize)>> {
+    Ok(parse::split_columns(input.trim_end(), ',', 1)
+        .map(|(column, part)| parse::parse_range(part, 1, column))
+        .collect::<Result<Vec<(usize, usize)>, _>>()?)
 }
 
 fn generate_base_strings(size: usize) -> Vec<String> {
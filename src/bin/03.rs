@@ -2,7 +2,6 @@ use adv_code_2025::*;
 use anyhow::*;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use std::cmp::max;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -18,6 +17,7 @@ const TEST: &str = "\
 
 fn main() -> Result<()> {
     start_day(DAY);
+    parallel::init_thread_pool()?;
 
     //region Part 1
     println!("=== Part 1 ===");
@@ -52,18 +52,11 @@ fn main() -> Result<()> {
 
 fn parse_input<R: BufRead>(reader: R) -> Result<Vec<Vec<i32>>> {
     let mut ret = Vec::new();
-    let lines = reader.lines().collect::<Result<Vec<String>, _>>()?;
-
-    for line in lines {
-        let bank = line
-            .chars()
-            .map(|c| {
-                c.to_digit(10)
-                    .map(|d| d as i32)
-                    .ok_or_else(|| anyhow!("Invalid digit"))
-            })
-            .collect::<Result<Vec<i32>, _>>()?;
-        ret.push(bank);
+
+    for line in parse::numbered_lines(reader) {
+        let (line_no, line) = line?;
+        let bank = parse::digits(&line, line_no)?;
+        ret.push(bank.into_iter().map(i32::from).collect());
     }
 
     Ok(ret)
@@ -86,15 +79,15 @@ fn select_max(numbers: &[i32], idx_from: usize, remaining: usize) -> Result<Vec<
     let mut max_value: Option<i32> = None;
     let mut max_idx = -1;
 
-    for i in idx_from..=idx_to {
-        if max_value.is_none() || numbers[i] > max_value.unwrap() {
-            max_value = Some(numbers[i]);
+    for (i, &number) in numbers.iter().enumerate().take(idx_to + 1).skip(idx_from) {
+        if max_value.is_none() || number > max_value.unwrap() {
+            max_value = Some(number);
             max_idx = i as i32;
         }
     }
 
     if max_idx == -1 {
-        return Err(anyhow!(("No maximum found")));
+        return Err(anyhow!("No maximum found"));
     }
 
     let mut ret = vec![max_value.unwrap()];
@@ -103,7 +96,7 @@ fn select_max(numbers: &[i32], idx_from: usize, remaining: usize) -> Result<Vec<
     Ok(ret)
 }
 
-fn select_max_joltage(bank: &Vec<i32>, selection_size: usize) -> Result<usize> {
+fn select_max_joltage(bank: &[i32], selection_size: usize) -> Result<usize> {
     let selection = select_max(bank, 0, selection_size)?
         .iter()
         .map(|j| j.to_string())
@@ -114,11 +107,7 @@ fn select_max_joltage(bank: &Vec<i32>, selection_size: usize) -> Result<usize> {
 
 fn solve<R: BufRead>(reader: R, selection_size: usize) -> Result<usize> {
     let banks = parse_input(reader)?;
-    let answer = banks
-        .iter()
-        .map(|bank| select_max_joltage(bank, selection_size))
-        .collect::<Result<Vec<usize>>>()?
-        .iter()
-        .sum::<usize>();
-    Ok(answer)
+    parallel::map_units(&banks, |bank| select_max_joltage(bank, selection_size))
+        .into_iter()
+        .sum()
 }
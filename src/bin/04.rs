@@ -73,7 +73,7 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn find_accessible_rolls(grid: &Vec<Vec<Cell>>) -> Vec<(usize, usize)> {
+fn find_accessible_rolls(grid: &[Vec<Cell>]) -> Vec<(usize, usize)> {
     let mut ret = vec![];
     let h = grid.len();
     let w = grid[0].len();
@@ -95,7 +95,7 @@ fn find_accessible_rolls(grid: &Vec<Vec<Cell>>) -> Vec<(usize, usize)> {
     ret
 }
 
-fn count_accessible_rolls(grid: &Vec<Vec<Cell>>) -> usize {
+fn count_accessible_rolls(grid: &[Vec<Cell>]) -> usize {
     let mut ret = 0;
     let h = grid.len();
     let w = grid[0].len();
@@ -118,7 +118,7 @@ fn count_accessible_rolls(grid: &Vec<Vec<Cell>>) -> usize {
 }
 
 fn count_filled_neighbors(
-    grid: &Vec<Vec<Cell>>,
+    grid: &[Vec<Cell>],
     height: usize,
     width: usize,
     row: usize,
@@ -153,24 +153,11 @@ fn count_filled_neighbors(
 }
 
 fn parse_input<R: BufRead>(reader: R) -> Result<Vec<Vec<Cell>>> {
-    let lines = reader.lines();
-    let mut grid = Vec::new();
-
-    for line in lines {
-        let line = line?;
-        let mut row = Vec::new();
-        for ch in line.chars() {
-            let cell = match ch {
-                '@' => Cell::Filled,
-                '.' => Cell::Empty,
-                _ => return Err(anyhow!("Invalid character in input: {}", ch)),
-            };
-            row.push(cell);
-        }
-        grid.push(row);
-    }
-
-    Ok(grid)
+    parse::char_grid(reader, |ch| match ch {
+        '@' => Some(Cell::Filled),
+        '.' => Some(Cell::Empty),
+        _ => None,
+    })
 }
 
 #[derive(Debug, PartialEq)]
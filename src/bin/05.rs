@@ -2,6 +2,8 @@ use adv_code_2025::*;
 use anyhow::*;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
+use itertools::Itertools;
+use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -29,14 +31,13 @@ fn main() -> Result<()> {
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let (intervals, food) = parse_input(reader)?;
+        let inventory = parse_inventory(reader)?;
+        let fresh_ranges = FreshRanges::new(inventory.fresh_ranges);
         let mut answer = 0;
 
-        for &item in &food {
-            if intervals
-                .iter()
-                .any(|&(start, end)| item >= start && item <= end)
-            {
+        // The ID section is streamed, so only the merged ranges are kept in memory
+        for id in inventory.available_ids {
+            if fresh_ranges.contains(id?) {
                 answer += 1;
             }
         }
@@ -55,14 +56,9 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let mut intervals = parse_intervals(reader)?;
-        merge_intervals(&mut intervals);
-
-        let mut answer = 0;
-        for interval in &intervals {
-            answer += (interval.1 - interval.0 + 1) as usize;
-        }
-        Ok(answer)
+        let inventory = parse_inventory(reader)?;
+        let fresh_ranges = FreshRanges::new(inventory.fresh_ranges);
+        Ok(fresh_ranges.size())
     }
 
     assert_eq!(14, part2(BufReader::new(TEST.as_bytes()))?);
@@ -72,74 +68,347 @@ fn main() -> Result<()> {
     println!("Result = {}", result);
     //endregion
 
+    //region Report
+    if cli_flag("report") {
+        println!("\n=== Ingredient Report ===");
+
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let report = build_report(parse_inventory(input_file)?)?;
+        print!("{}", report);
+    }
+    //endregion
+
     Ok(())
 }
 
 type FoodId = i64;
+type Interval = (FoodId, FoodId);
 
-fn parse_input<R: BufRead>(reader: R) -> Result<(Vec<(FoodId, FoodId)>, Vec<FoodId>)> {
-    let mut intervals = Vec::new();
-    let mut food = Vec::new();
-    let regex_interval = regex::Regex::new(r"^(\d+)-(\d+)$")?;
-
-    for line in reader.lines() {
-        let line = line?.trim().to_string();
-        if regex_interval.is_match(&line) {
-            let caps = regex_interval.captures(&line).unwrap();
-            let start = caps.get(1).unwrap().as_str().parse()?;
-            let end = caps.get(2).unwrap().as_str().parse()?;
-            intervals.push((start, end));
-        } else if !line.is_empty() {
-            let value = line.parse()?;
-            food.push(value);
+/// Merged, non-overlapping fresh ranges sorted by start, answering
+/// membership queries by binary search.
+#[derive(Debug)]
+struct FreshRanges {
+    intervals: Vec<Interval>,
+}
+
+impl FreshRanges {
+    fn new(ranges: Vec<Interval>) -> Self {
+        Self::with_sources(&ranges).0
+    }
+
+    /// Merges `ranges`, additionally returning for each merged interval the
+    /// indices of the original ranges it was built from.
+    fn with_sources(ranges: &[Interval]) -> (Self, Vec<Vec<usize>>) {
+        let mut order: Vec<usize> = (0..ranges.len()).collect();
+        order.sort_unstable_by_key(|&i| ranges[i]);
+
+        let mut intervals: Vec<Interval> = Vec::new();
+        let mut sources: Vec<Vec<usize>> = Vec::new();
+        for i in order {
+            match intervals.last_mut() {
+                Some(last) if has_overlap(*last, ranges[i]) => {
+                    *last = merge(*last, ranges[i]);
+                    sources.last_mut().unwrap().push(i);
+                }
+                _ => {
+                    intervals.push(ranges[i]);
+                    sources.push(vec![i]);
+                }
+            }
         }
+        sources.iter_mut().for_each(|s| s.sort_unstable());
+
+        (FreshRanges { intervals }, sources)
+    }
+
+    fn contains(&self, id: FoodId) -> bool {
+        self.position(id).is_some()
     }
 
-    Ok((intervals, food))
+    /// Index of the merged interval containing `id`.
+    fn position(&self, id: FoodId) -> Option<usize> {
+        // Index of the first interval starting after `id`; only its predecessor can contain `id`
+        let idx = self.intervals.partition_point(|&(start, _)| start <= id);
+        (idx > 0 && id <= self.intervals[idx - 1].1).then(|| idx - 1)
+    }
+
+    fn size(&self) -> usize {
+        self.intervals
+            .iter()
+            .map(|&(start, end)| (end - start + 1) as usize)
+            .sum()
+    }
+}
+
+/// Puzzle input: the fresh ranges section, followed by a blank line and the
+/// available ingredient IDs, which are parsed lazily while iterating.
+struct Inventory<B: BufRead> {
+    fresh_ranges: Vec<Interval>,
+    available_ids: AvailableIds<B>,
+}
+
+struct AvailableIds<B: BufRead> {
+    lines: parse::NumberedLines<B>,
 }
 
-fn parse_intervals<R: BufRead>(reader: R) -> Result<Vec<(FoodId, FoodId)>> {
-    let mut intervals = Vec::new();
-    let regex_interval = regex::Regex::new(r"^(\d+)-(\d+)$")?;
+impl<B: BufRead> Iterator for AvailableIds<B> {
+    type Item = Result<FoodId>;
 
-    for line in reader.lines() {
-        let line = line?.trim().to_string();
-        if regex_interval.is_match(&line) {
-            let caps = regex_interval.captures(&line).unwrap();
-            let start = caps.get(1).unwrap().as_str().parse()?;
-            let end = caps.get(2).unwrap().as_str().parse()?;
-            intervals.push((start, end));
-        } else {
-            break;
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in self.lines.by_ref() {
+            let (line_no, line) = match line {
+                Result::Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if !line.trim().is_empty() {
+                return Some(parse_food_id(&line, line_no));
+            }
         }
+        None
+    }
+}
+
+/// Parses the fresh ranges up to the separating blank line and leaves the
+/// remaining lines for the `available_ids` iterator.
+fn parse_inventory<B: BufRead>(reader: B) -> Result<Inventory<B>> {
+    let mut lines = parse::numbered_lines(reader);
+    let fresh_ranges = parse::next_section(&mut lines)?
+        .iter()
+        .map(|(line_no, line)| parse::parse_range(line.trim_end(), *line_no, 1))
+        .collect::<Result<Vec<Interval>, _>>()?;
+
+    Ok(Inventory {
+        fresh_ranges,
+        available_ids: AvailableIds { lines },
+    })
+}
+
+fn parse_food_id(s: &str, line_no: usize) -> Result<FoodId> {
+    let id = parse::parse_value::<FoodId>(s, line_no, 1)?;
+    if id < 0 {
+        return Err(
+            parse::ParseError::new(line_no, 1, format!("negative ingredient ID {}", id)).into(),
+        );
+    }
+    Ok(id)
+}
+
+#[derive(Debug)]
+struct IngredientStatus {
+    id: FoodId,
+    /// Indices of the original fresh ranges containing the ID; empty if spoiled
+    covering_ranges: Vec<usize>,
+}
+
+impl IngredientStatus {
+    fn is_fresh(&self) -> bool {
+        !self.covering_ranges.is_empty()
     }
+}
 
-    Ok(intervals)
+#[derive(Debug)]
+struct MergedCoverage {
+    interval: Interval,
+    source_ranges: Vec<usize>,
+    available_ids: usize,
 }
 
-fn merge_intervals(intervals: &mut Vec<(FoodId, FoodId)>) {
-    if intervals.len() <= 1 {
-        return;
+#[derive(Debug)]
+struct IngredientReport {
+    num_ranges: usize,
+    ingredients: Vec<IngredientStatus>,
+    coverage: Vec<MergedCoverage>,
+}
+
+impl IngredientReport {
+    fn num_fresh(&self) -> usize {
+        self.ingredients.iter().filter(|i| i.is_fresh()).count()
     }
+}
 
-    intervals.sort_by_key(|&(start, _)| start);
-    let mut i = 0;
-    while i < intervals.len() - 1 {
-        if has_overlap(intervals[i], intervals[i + 1]) {
-            let merged = merge(intervals[i], intervals[i + 1]);
-            intervals.splice(i..=i + 1, [merged]);
-        } else {
-            i += 1;
+impl Display for IngredientReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for ingredient in &self.ingredients {
+            if ingredient.is_fresh() {
+                writeln!(
+                    f,
+                    "{}: fresh (ranges {})",
+                    ingredient.id,
+                    ingredient.covering_ranges.iter().join(", ")
+                )?;
+            } else {
+                writeln!(f, "{}: spoiled", ingredient.id)?;
+            }
         }
+
+        let num_fresh = self.num_fresh();
+        writeln!(
+            f,
+            "\n{} ingredients: {} fresh, {} spoiled",
+            self.ingredients.len(),
+            num_fresh,
+            self.ingredients.len() - num_fresh
+        )?;
+        writeln!(
+            f,
+            "{} fresh ranges merged into {} intervals:",
+            self.num_ranges,
+            self.coverage.len()
+        )?;
+        for coverage in &self.coverage {
+            let (start, end) = coverage.interval;
+            writeln!(
+                f,
+                "{}-{}: {} IDs, {} available, from ranges {}",
+                start,
+                end,
+                end - start + 1,
+                coverage.available_ids,
+                coverage.source_ranges.iter().join(", ")
+            )?;
+        }
+
+        fmt::Result::Ok(())
+    }
+}
+
+fn build_report<B: BufRead>(inventory: Inventory<B>) -> Result<IngredientReport> {
+    let ranges = inventory.fresh_ranges;
+    let (fresh_ranges, sources) = FreshRanges::with_sources(&ranges);
+    let mut coverage: Vec<MergedCoverage> = fresh_ranges
+        .intervals
+        .iter()
+        .zip(sources)
+        .map(|(&interval, source_ranges)| MergedCoverage {
+            interval,
+            source_ranges,
+            available_ids: 0,
+        })
+        .collect();
+
+    let mut ingredients = Vec::new();
+    for id in inventory.available_ids {
+        let id = id?;
+        let covering_ranges = match fresh_ranges.position(id) {
+            Some(pos) => {
+                coverage[pos].available_ids += 1;
+                // Only the ranges merged into this interval can contain the ID
+                coverage[pos]
+                    .source_ranges
+                    .iter()
+                    .copied()
+                    .filter(|&i| ranges[i].0 <= id && id <= ranges[i].1)
+                    .collect()
+            }
+            None => vec![],
+        };
+        ingredients.push(IngredientStatus {
+            id,
+            covering_ranges,
+        });
     }
+
+    Ok(IngredientReport {
+        num_ranges: ranges.len(),
+        ingredients,
+        coverage,
+    })
 }
 
-fn has_overlap(a: (FoodId, FoodId), b: (FoodId, FoodId)) -> bool {
+fn has_overlap(a: Interval, b: Interval) -> bool {
     let x = a.0.max(b.0);
     let y = a.1.min(b.1);
     x <= y
 }
 
-fn merge(a: (FoodId, FoodId), b: (FoodId, FoodId)) -> (FoodId, FoodId) {
+fn merge(a: Interval, b: Interval) -> Interval {
     (a.0.min(b.0), a.1.max(b.1))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_ranges_contains() {
+        let fresh_ranges = FreshRanges::new(vec![(16, 20), (3, 5), (12, 18), (10, 14)]);
+        assert_eq!(fresh_ranges.intervals, vec![(3, 5), (10, 20)]);
+
+        let fresh: Vec<FoodId> = (0..25).filter(|&id| fresh_ranges.contains(id)).collect();
+        assert_eq!(
+            fresh,
+            vec![3, 4, 5, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20]
+        );
+    }
+
+    #[test]
+    fn test_build_report() -> Result<()> {
+        let report = build_report(parse_inventory(TEST.as_bytes())?)?;
+        let statuses = report
+            .ingredients
+            .iter()
+            .map(|i| (i.id, i.covering_ranges.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                (1, vec![]),
+                (5, vec![0]),
+                (8, vec![]),
+                (11, vec![1]),
+                (17, vec![2, 3]),
+                (32, vec![]),
+            ]
+        );
+        assert_eq!(report.num_fresh(), 3);
+
+        let coverage = report
+            .coverage
+            .iter()
+            .map(|c| (c.interval, c.source_ranges.clone(), c.available_ids))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            coverage,
+            vec![((3, 5), vec![0], 1), ((10, 20), vec![1, 2, 3], 2)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_inventory_crlf() -> Result<()> {
+        let input = "3-5\r\n10-14\r\n\r\n1\r\n5\r\n";
+        let inventory = parse_inventory(input.as_bytes())?;
+        assert_eq!(inventory.fresh_ranges, vec![(3, 5), (10, 14)]);
+        let ids = inventory.available_ids.collect::<Result<Vec<FoodId>>>()?;
+        assert_eq!(ids, vec![1, 5]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_inventory_errors() {
+        let err = parse_inventory("3-5\n14-10\n\n1\n".as_bytes())
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Line 2, column 1: reversed range '14-10'");
+
+        let err = parse_inventory("3-5\n7\n\n1\n".as_bytes()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Line 2, column 1: expected a range 'a-b', got '7'"
+        );
+
+        let inventory = parse_inventory("3-5\n\n1\n2-4\n".as_bytes()).unwrap();
+        let err = inventory.available_ids.last().unwrap().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 4, column 1: invalid value '2-4': invalid digit found in string"
+        );
+
+        let inventory = parse_inventory("3-5\n\n-1\n".as_bytes()).unwrap();
+        let err = inventory.available_ids.last().unwrap().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 3, column 1: negative ingredient ID -1"
+        );
+    }
+}
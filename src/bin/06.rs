@@ -1,6 +1,9 @@
 use anyhow::*;
+use itertools::{EitherOrBoth, Itertools};
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use adv_code_2025::*;
@@ -17,12 +20,13 @@ const TEST: &str = "\
 
 fn main() -> Result<()> {
     start_day(DAY);
+    parallel::init_thread_pool()?;
 
     //region Part 1
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        solve(reader, &parse_input)
+        solve(reader, Reading::Rows)
     }
 
     assert_eq!(4277556, part1(BufReader::new(TEST.as_bytes()))?);
@@ -36,7 +40,7 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        solve(reader, &parse_input2)
+        solve(reader, Reading::Columns)
     }
 
     assert_eq!(3263827, part2(BufReader::new(TEST.as_bytes()))?);
@@ -46,131 +50,363 @@ fn main() -> Result<()> {
     println!("Result = {}", result);
     //endregion
 
+    //region Explain
+    if cli_flag("explain") {
+        println!("\n=== Explain ===");
+
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        print!("{}", explain(input_file)?);
+    }
+    //endregion
+
+    //region Wide evaluation
+    if cli_flag("u128") {
+        println!("\n=== Part 1 / Part 2 (u128) ===");
+
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let result = solve::<u128, _>(input_file, Reading::Rows)?;
+        println!("Part 1 = {}", result);
+
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let result = solve::<u128, _>(input_file, Reading::Columns)?;
+        println!("Part 2 = {}", result);
+    }
+    //endregion
+
     Ok(())
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Operator {
     Add,
     Multiply,
+    /// First operand minus all following ones
+    Subtract,
+    Min,
+    Max,
+    /// Decimal concatenation of the operands, e.g. `12 | 3 = 123`
+    Concat,
 }
 
-fn solve<R: BufRead>(reader: R, parser: &dyn Fn(R) -> Result<Vec<(Operator, Vec<usize>)>>) -> Result<usize> {
-    let mut answer = 0;
-    let operations = parser(reader)?;
+impl Operator {
+    fn symbol(&self) -> char {
+        match self {
+            Operator::Add => '+',
+            Operator::Multiply => '*',
+            Operator::Subtract => '-',
+            Operator::Min => '<',
+            Operator::Max => '>',
+            Operator::Concat => '|',
+        }
+    }
+
+    fn from_char(c: char) -> Option<Operator> {
+        match c {
+            '+' => Some(Operator::Add),
+            '*' => Some(Operator::Multiply),
+            '-' => Some(Operator::Subtract),
+            '<' => Some(Operator::Min),
+            '>' => Some(Operator::Max),
+            '|' => Some(Operator::Concat),
+            _ => None,
+        }
+    }
 
-    for (op, operands) in operations {
-        let result: usize = match op {
-            Operator::Add => operands.iter().sum(),
-            Operator::Multiply => operands.iter().product(),
-        };
-        answer += result;
+    /// Applies the operator to the operands from left to right. Fails with
+    /// "Arithmetic underflow" for `Subtract` and "Arithmetic overflow" for
+    /// the others if the result does not fit, or if there are no operands.
+    fn apply<T: Value>(&self, operands: &[T]) -> Result<T> {
+        let (&first, rest) = operands
+            .split_first()
+            .ok_or_else(|| anyhow!("No operands"))?;
+        rest.iter()
+            .try_fold(first, |acc, &operand| match self {
+                Operator::Add => acc.checked_add(operand),
+                Operator::Multiply => acc.checked_mul(operand),
+                Operator::Subtract => acc.checked_sub(operand),
+                Operator::Min => Some(acc.min(operand)),
+                Operator::Max => Some(acc.max(operand)),
+                Operator::Concat => acc.checked_concat(operand),
+            })
+            .ok_or_else(|| match self {
+                Operator::Subtract => anyhow!("Arithmetic underflow"),
+                _ => anyhow!("Arithmetic overflow"),
+            })
     }
+}
 
-    Ok(answer)
+/// Unsigned integer type a worksheet is evaluated in.
+trait Value: Copy + Ord + FromStr + Display {
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_concat(self, other: Self) -> Option<Self>;
 }
 
-fn parse_input<R: BufRead>(reader: R) -> Result<Vec<(Operator, Vec<usize>)>> {
-    let mut all_operands: Vec<Vec<usize>> = vec![];
-    let mut operators: Vec<Operator> = vec![];
-
-    for line in reader.lines() {
-        let line = line?;
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        let first = parts[0];
-        match first {
-            "+" | "*" => {
-                operators = parts
-                    .iter()
-                    .map(|s| match *s {
-                        "+" => Ok(Operator::Add),
-                        "*" => Ok(Operator::Multiply),
-                        _ => Err(anyhow!("Invalid operator: {}", s)),
-                    })
-                    .collect::<Result<Vec<Operator>, _>>()?;
+macro_rules! impl_value {
+    ($t:ty) => {
+        impl Value for $t {
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
             }
-            _ => {
-                let operands = parts
-                    .iter()
-                    .map(|s| s.parse::<usize>())
-                    .collect::<Result<Vec<usize>, _>>()?;
-                if !all_operands.is_empty() {
-                    for (i, operand) in operands.iter().enumerate() {
-                        all_operands[i].push(*operand);
-                    }
-                } else {
-                    all_operands = operands.iter().map(|i| vec![*i]).collect();
-                }
+
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+
+            fn checked_concat(self, other: Self) -> Option<Self> {
+                let shift = (10 as $t).checked_pow(other.checked_ilog10().unwrap_or(0) + 1)?;
+                self.checked_mul(shift)?.checked_add(other)
             }
         }
+    };
+}
+
+impl_value!(usize);
+impl_value!(u128);
+
+/// How the digits of a problem block are grouped into numbers.
+#[derive(Debug, Clone, Copy)]
+enum Reading {
+    /// Every row is one number (part 1)
+    Rows,
+    /// Every column is one number, read top to bottom, columns right to left (part 2)
+    Columns,
+}
+
+/// A single problem of the worksheet: its operator and the character block
+/// above it, spanning from the operator column up to the next problem.
+#[derive(Debug)]
+struct Problem {
+    operator: Operator,
+    column: usize,
+    block: Vec<Vec<char>>,
+}
+
+impl Problem {
+    fn operands<T: Value>(&self, reading: Reading) -> Result<Vec<T>> {
+        match reading {
+            Reading::Rows => self.row_operands(),
+            Reading::Columns => self.column_operands(),
+        }
     }
 
-    let ret = operators
-        .iter()
-        .zip(all_operands)
-        .map(|(op, ops)| (op.clone(), ops))
-        .collect();
+    fn row_operands<T: Value>(&self) -> Result<Vec<T>> {
+        self.block
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .filter(|row| !row.trim().is_empty())
+            .map(|row| self.parse_number(row.trim()))
+            .collect()
+    }
+
+    fn column_operands<T: Value>(&self) -> Result<Vec<T>> {
+        let width = self.block.first().map_or(0, |row| row.len());
+        (0..width)
+            .rev()
+            .map(|x| {
+                self.block
+                    .iter()
+                    .map(|row| row[x])
+                    .filter(|&c| c != ' ')
+                    .collect::<String>()
+            })
+            .filter(|column| !column.is_empty())
+            .map(|column| self.parse_number(&column))
+            .collect()
+    }
+
+    fn parse_number<T: Value>(&self, s: &str) -> Result<T> {
+        s.parse::<T>().map_err(|_| {
+            anyhow!(
+                "Invalid number '{}' in problem at column {}",
+                s,
+                self.column + 1
+            )
+        })
+    }
+
+    fn evaluate<T: Value>(&self, reading: Reading) -> Result<T> {
+        let operands = self.operands::<T>(reading)?;
+        if operands.is_empty() {
+            return Err(anyhow!("Problem at column {} has no operands", self.column + 1));
+        }
+        self.operator
+            .apply(&operands)
+            .map_err(|err| anyhow!("{} in problem at column {}", err, self.column + 1))
+    }
+
+    /// Renders the operands as an equation like `4 + 431 + 623 = 1058`.
+    fn equation(&self, reading: Reading) -> Result<String> {
+        let operands = self.operands::<usize>(reading)?;
+        let result = self.evaluate::<usize>(reading)?;
+        let lhs = operands
+            .iter()
+            .join(&format!(" {} ", self.operator.symbol()));
+        Ok(format!("{} = {}", lhs, result))
+    }
+
+    /// The source block with the operator row below it, one string per line.
+    fn source_lines(&self) -> Vec<String> {
+        let width = self.block.first().map_or(1, |row| row.len().max(1));
+        let mut lines: Vec<String> = self.block.iter().map(|row| row.iter().collect()).collect();
+        lines.push(format!("{:<width$}", self.operator.symbol(), width = width));
+        lines
+    }
+}
+
+/// Renders every problem's source block next to its part 1 and part 2 equations.
+fn explain<R: BufRead>(reader: R) -> Result<String> {
+    let mut ret = String::new();
+
+    for problem in parse_worksheet(reader)? {
+        let annotations = [
+            format!("column {}", problem.column + 1),
+            format!("part 1: {}", problem.equation(Reading::Rows)?),
+            format!("part 2: {}", problem.equation(Reading::Columns)?),
+        ];
+        let source = problem.source_lines();
+        let width = source[0].chars().count();
+        for line in source.iter().zip_longest(&annotations) {
+            let (src, annotation) = match line {
+                EitherOrBoth::Both(src, annotation) => (src.as_str(), annotation.as_str()),
+                EitherOrBoth::Left(src) => (src.as_str(), ""),
+                EitherOrBoth::Right(annotation) => ("", annotation.as_str()),
+            };
+            ret.push_str(format!("{:<width$} | {}", src, annotation).trim_end());
+            ret.push('\n');
+        }
+        ret.push('\n');
+    }
 
     Ok(ret)
 }
 
-fn parse_input2<R: BufRead>(reader: R) -> Result<Vec<(Operator, Vec<usize>)>> {
-    let mut lines = reader
-        .lines()
-        .flat_map(|line| line.ok())
-        .collect::<Vec<String>>();
-
-    let operators = lines
-        .last()
-        .ok_or_else(|| anyhow!("No lines found"))?
-        .chars()
-        .flat_map(|c| match c {
-            '+' => Some(Operator::Add),
-            '*' => Some(Operator::Multiply),
-            _ => None,
-        })
-        .collect::<Vec<Operator>>();
+fn solve<T: Value + From<u8> + Send, R: BufRead>(reader: R, reading: Reading) -> Result<T> {
+    let mut answer = T::from(0);
+    let problems = parse_worksheet(reader)?;
+
+    for result in parallel::map_units(&problems, |problem| problem.evaluate::<T>(reading)) {
+        answer = answer
+            .checked_add(result?)
+            .ok_or_else(|| anyhow!("Arithmetic overflow in grand total"))?;
+    }
 
-    let operands_lines = &lines[..lines.len() - 1];
-    let max_len = operands_lines
+    Ok(answer)
+}
+
+/// Splits the worksheet into problems using the operator positions in the
+/// last line: each problem starts at its operator's column and ends before
+/// the next one, with trailing blank columns removed. Shorter rows are padded
+/// with spaces.
+fn parse_worksheet<R: BufRead>(reader: R) -> Result<Vec<Problem>> {
+    let mut lines = reader.lines().collect::<Result<Vec<String>, _>>()?;
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let operator_line = lines.pop().ok_or_else(|| anyhow!("No lines found"))?;
+    let rows: Vec<Vec<char>> = lines.iter().map(|line| line.chars().collect()).collect();
+    let width = rows
         .iter()
-        .map(|line| line.chars().count())
+        .map(|row| row.len())
+        .chain([operator_line.chars().count()])
         .max()
-        .ok_or_else(|| anyhow!("No operand lines found"))?;
+        .unwrap_or(0);
 
-    let mut column_strs = vec![String::new(); max_len];
-    for operand_line in operands_lines {
-        for (i, c) in operand_line.chars().enumerate() {
-            if c != ' ' {
-                column_strs[i].push(c);
-            }
+    let mut operators = vec![];
+    for (x, c) in operator_line.chars().enumerate() {
+        if c == ' ' {
+            continue;
         }
+        let op = Operator::from_char(c)
+            .ok_or_else(|| anyhow!("Invalid operator '{}' at column {}", c, x + 1))?;
+        operators.push((x, op));
     }
 
-    let mut all_operands: Vec<Vec<usize>> = vec![];
-    let mut operands: Vec<usize> = vec![];
+    let mut problems = vec![];
+    for (i, &(column, operator)) in operators.iter().enumerate() {
+        let next = operators.get(i + 1).map_or(width, |&(x, _)| x);
+        let mut block: Vec<Vec<char>> = rows
+            .iter()
+            .map(|row| (column..next).map(|x| *row.get(x).unwrap_or(&' ')).collect())
+            .collect();
 
-    for column_str in column_strs {
-        if column_str.is_empty() {
-            if !operands.is_empty() {
-                all_operands.push(operands);
-                operands = vec![];
-            }
-            continue;
+        while !block.is_empty() && block.iter().all(|row| row.last() == Some(&' ')) {
+            block.iter_mut().for_each(|row| {
+                row.pop();
+            });
         }
-        let operand = column_str.parse::<usize>()?;
-        operands.push(operand);
+
+        problems.push(Problem {
+            operator,
+            column,
+            block,
+        });
     }
 
-    if !operands.is_empty() {
-        all_operands.push(operands);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_worksheet_ragged_rows() -> Result<()> {
+        let input = "12 3\n4\n*  +\n";
+        let problems = parse_worksheet(input.as_bytes())?;
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].row_operands::<usize>()?, vec![12, 4]);
+        assert_eq!(problems[0].column_operands::<usize>()?, vec![2, 14]);
+        assert_eq!(problems[1].column, 3);
+        assert_eq!(problems[1].row_operands::<usize>()?, vec![3]);
+        Ok(())
     }
 
-    let ret = operators
-        .iter()
-        .zip(all_operands)
-        .map(|(op, ops)| (op.clone(), ops))
-        .collect();
+    #[test]
+    fn test_explain() -> Result<()> {
+        let explanation = explain(TEST.as_bytes())?;
+        let last_problem = explanation.split("\n\n").nth(3).unwrap();
+        assert_eq!(
+            last_problem,
+            "\
+64  | column 13
+23  | part 1: 64 + 23 + 314 = 401
+314 | part 2: 4 + 431 + 623 = 1058
++   |"
+        );
+        Ok(())
+    }
 
-    Ok(ret)
+    #[test]
+    fn test_extended_operators() -> Result<()> {
+        let input = "20 3  5 12\n 4 7 10  3\n-  < >  |\n";
+        let problems = parse_worksheet(input.as_bytes())?;
+        let results = problems
+            .iter()
+            .map(|p| p.evaluate::<usize>(Reading::Rows))
+            .collect::<Result<Vec<usize>>>()?;
+        assert_eq!(results, vec![16, 3, 10, 123]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow_is_reported() -> Result<()> {
+        let input = format!("1 {}\n2    2\n+ *\n", usize::MAX / 2 + 1);
+        let err = solve::<usize, _>(input.as_bytes(), Reading::Rows).unwrap_err();
+        assert_eq!(err.to_string(), "Arithmetic overflow in problem at column 3");
+        assert_eq!(
+            solve::<u128, _>(input.as_bytes(), Reading::Rows)?,
+            usize::MAX as u128 + 4
+        );
+
+        let err = solve::<usize, _>("1 3\n2 5\n+ -\n".as_bytes(), Reading::Rows).unwrap_err();
+        assert_eq!(err.to_string(), "Arithmetic underflow in problem at column 3");
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use itertools::Itertools;
+use std::fmt::Display;
 use anyhow::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -52,7 +53,7 @@ fn main() -> Result<()> {
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
         let setup = parse_input(reader)?;
-        let answer = count_paths(&setup);
+        let answer = count_paths(&setup)?;
         Ok(answer)
     }
 
@@ -63,110 +64,362 @@ fn main() -> Result<()> {
     println!("Result = {}", result);
     //endregion
 
+    //region Wide counting
+    if cli_flag("u128") {
+        println!("\n=== Part 2 (u128) ===");
+
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let result = count_paths::<u128>(&parse_input(input_file)?)?;
+        println!("Result = {}", result);
+    }
+    //endregion
+
+    //region Rendering
+    if cli_flag("render") {
+        println!("\n=== Beams ===");
+
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        print!("{}", render_text(&parse_input(input_file)?)?);
+    }
+
+    if let Some(path) = cli_option("render-ppm") {
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        std::fs::write(&path, render_ppm(&parse_input(input_file)?, 4)?)?;
+        println!("\nBeam image written to {}", path);
+    }
+    //endregion
+
     Ok(())
 }
 
+/// A cell of the manifold. Beams travel downwards; every element defines at
+/// which column offsets an incoming beam continues in the same row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Element {
+    Empty,
+    /// Emits a new beam downwards; incoming beams pass through
+    Source,
+    Splitter,
+    /// Mirror shifting the beam one column to the left
+    MirrorLeft,
+    /// Mirror shifting the beam one column to the right
+    MirrorRight,
+    Absorber,
+}
+
+impl Element {
+    fn from_char(ch: char) -> Option<Element> {
+        match ch {
+            '.' => Some(Element::Empty),
+            'S' => Some(Element::Source),
+            '^' => Some(Element::Splitter),
+            '<' => Some(Element::MirrorLeft),
+            '>' => Some(Element::MirrorRight),
+            '#' => Some(Element::Absorber),
+            _ => None,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Element::Empty => '.',
+            Element::Source => 'S',
+            Element::Splitter => '^',
+            Element::MirrorLeft => '<',
+            Element::MirrorRight => '>',
+            Element::Absorber => '#',
+        }
+    }
+
+    /// Column offsets at which an incoming beam leaves the cell.
+    fn outputs(&self) -> &'static [isize] {
+        match self {
+            Element::Empty | Element::Source => &[0],
+            Element::Splitter => &[-1, 1],
+            Element::MirrorLeft => &[-1],
+            Element::MirrorRight => &[1],
+            Element::Absorber => &[],
+        }
+    }
+
+    fn is_splitter(&self) -> bool {
+        matches!(self, Element::Splitter)
+    }
+}
+
 #[derive(Debug)]
 struct Setup {
-    start: usize,
     width: usize,
-    splitters: Vec<HashSet<usize>>,
+    grid: Vec<Vec<Element>>,
 }
 
-fn count_paths(setup: &Setup) -> usize {
-    let mut beams = HashMap::new();
-    beams.insert(setup.start, 1_usize);
+/// Number of timelines a beam stands for. `bool` only tracks whether a beam
+/// is present at all.
+trait Count: Copy + Default + PartialEq + Display {
+    const ONE: Self;
 
-    for splitter_row in &setup.splitters {
-        let mut new_beams = HashMap::new();
-        for splitter in splitter_row {
-            if let Some(&count) = beams.get(splitter) {
-                // Split the beam
-                if *splitter > 0 {
-                    *new_beams.entry(splitter - 1).or_insert(0) += count;
-                }
-                if *splitter + 1 < setup.width {
-                    *new_beams.entry(splitter + 1).or_insert(0) += count;
-                }
-                beams.remove(splitter);
+    fn checked_add(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_count {
+    ($t:ty) => {
+        impl Count for $t {
+            const ONE: Self = 1;
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
             }
         }
-        for (beam, count) in &beams {
-            *new_beams.entry(*beam).or_insert(0) += *count;
-        }
-        beams = new_beams;
+    };
+}
+
+impl_count!(usize);
+impl_count!(u128);
+
+impl Count for bool {
+    const ONE: Self = true;
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Some(self || other)
     }
+}
+
+fn count_paths<T: Count>(setup: &Setup) -> Result<T> {
+    let mut beams = vec![T::default(); setup.width];
 
-    beams.values().sum()
+    for (y, row) in setup.grid.iter().enumerate() {
+        beams = propagate(&beams, row).ok_or_else(|| overflow_error(y))?;
+    }
+
+    beams
+        .iter()
+        .try_fold(T::default(), |acc, &count| acc.checked_add(count))
+        .ok_or_else(|| anyhow!("Timeline count overflow when summing the last row"))
 }
 
-fn count_splits(setup: &Setup) -> usize {
-    let mut ret = 0;
-    let mut beams = HashSet::new();
-    beams.insert(setup.start);
-
-    for splitter_row in &setup.splitters {
-        let mut new_beams = HashSet::new();
-        for splitter in splitter_row {
-            if beams.contains(splitter) {
-                // Split the beam
-                if *splitter > 0 {
-                    new_beams.insert(splitter - 1);
-                }
-                if *splitter + 1 < setup.width {
-                    new_beams.insert(splitter + 1);
-                }
-                beams.remove(splitter);
-                ret += 1;
+/// Number of timelines per column in every row of the manifold, after the
+/// beams entering that row have been transformed by its elements.
+fn trace_timelines(setup: &Setup) -> Result<Vec<Vec<usize>>> {
+    let mut ret: Vec<Vec<usize>> = Vec::with_capacity(setup.grid.len());
+    let mut beams = vec![0; setup.width];
+
+    for (y, row) in setup.grid.iter().enumerate() {
+        beams = propagate(&beams, row).ok_or_else(|| overflow_error(y))?;
+        ret.push(beams.clone());
+    }
+
+    Ok(ret)
+}
+
+/// Moves the timeline counts entering a row through its elements and adds
+/// the beams emitted by its sources. Returns `None` if a count overflows.
+fn propagate<T: Count>(beams: &[T], row: &[Element]) -> Option<Vec<T>> {
+    let width = beams.len();
+    let mut new_beams = vec![T::default(); width];
+
+    for (x, &count) in beams.iter().enumerate() {
+        if count == T::default() {
+            continue;
+        }
+        for &offset in row[x].outputs() {
+            let target = x as isize + offset;
+            if target >= 0 && (target as usize) < width {
+                let target = target as usize;
+                new_beams[target] = new_beams[target].checked_add(count)?;
             }
         }
-        for beam in &beams {
-            new_beams.insert(*beam);
+    }
+
+    for (x, element) in row.iter().enumerate() {
+        if *element == Element::Source {
+            new_beams[x] = new_beams[x].checked_add(T::ONE)?;
         }
-        beams = new_beams;
     }
-    ret
+
+    Some(new_beams)
 }
 
-fn parse_input<R: BufRead>(reader: R) -> Result<Setup> {
-    let mut start = None;
-    let mut width = None;
-    let mut splitters: Vec<HashSet<usize>> = Vec::new();
-
-    for line in reader.lines() {
-        let line = line?;
-        if width.is_none() {
-            width = Some(line.len());
-        } else if width.unwrap() != line.len() {
-            return Err(anyhow!("Inconsistent line widths in input"));
-        }
-        let mut splitter_row: HashSet<usize> = HashSet::new();
-        for (x, ch) in line.chars().enumerate() {
-            match ch {
-                'S' => {
-                    if start.is_some() {
-                        return Err(anyhow!("Multiple start positions found"));
-                    }
-                    start = Some(x);
-                }
-                '^' => {
-                    splitter_row.insert(x);
+fn overflow_error(row: usize) -> Error {
+    anyhow!("Timeline count overflow in row {}", row + 1)
+}
+
+/// True if a beam enters the cell at (`x`, `y`).
+fn is_hit(timelines: &[Vec<usize>], x: usize, y: usize) -> bool {
+    y > 0 && timelines[y - 1][x] > 0
+}
+
+/// Draws the manifold with the beams overlaid: `|` is an active beam, elements
+/// hit by a beam keep their symbol and elements no beam reaches are drawn as
+/// `.` (sources are always shown). Each row is followed by its number of
+/// timelines, and the final line lists the timeline count of every column
+/// the beams leave the manifold through.
+fn render_text(setup: &Setup) -> Result<String> {
+    let timelines = trace_timelines(setup)?;
+    let mut ret = String::new();
+
+    for (y, row) in timelines.iter().enumerate() {
+        let line: String = (0..setup.width)
+            .map(|x| match setup.grid[y][x] {
+                Element::Source => 'S',
+                Element::Empty if row[x] > 0 => '|',
+                Element::Empty => '.',
+                element if is_hit(&timelines, x, y) => element.symbol(),
+                _ => '.',
+            })
+            .collect();
+        ret.push_str(&format!("{} {:>6}\n", line, row.iter().sum::<usize>()));
+    }
+
+    if let Some(last) = timelines.last() {
+        let counts = last
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(x, count)| format!("{}:{}", x, count))
+            .join(" ");
+        ret.push_str(&format!("Timelines per column: {}\n", counts));
+    }
+
+    Ok(ret)
+}
+
+/// Renders the manifold as a plain PPM image with `scale`×`scale` pixels per
+/// cell. Elements are grey (red once hit), sources are blue and beams are
+/// yellow, brighter the more timelines pass through the cell.
+fn render_ppm(setup: &Setup, scale: usize) -> Result<String> {
+    let timelines = trace_timelines(setup)?;
+    let max_count = timelines.iter().flatten().copied().max().unwrap_or(1).max(1) as f64;
+    let height = timelines.len();
+
+    let mut ret = format!("P3\n{} {}\n255\n", setup.width * scale, height * scale);
+    for (y, row) in timelines.iter().enumerate() {
+        let colors: Vec<(u8, u8, u8)> = (0..setup.width)
+            .map(|x| match setup.grid[y][x] {
+                Element::Source => (0, 200, 255),
+                Element::Empty if row[x] > 0 => {
+                    // Logarithmic brightness, since counts grow exponentially
+                    let level = (row[x] as f64).ln_1p() / max_count.ln_1p();
+                    let v = (80.0 + 175.0 * level) as u8;
+                    (v, v, 0)
                 }
-                '.' => {}
-                _ => return Err(anyhow!("Invalid character in input: {}", ch)),
-            }
+                Element::Empty => (20, 20, 30),
+                _ if is_hit(&timelines, x, y) => (220, 40, 40),
+                _ => (110, 110, 110),
+            })
+            .collect();
+        let line = colors
+            .iter()
+            .flat_map(|&(r, g, b)| std::iter::repeat_n(format!("{} {} {}", r, g, b), scale))
+            .join(" ");
+        for _ in 0..scale {
+            ret.push_str(&line);
+            ret.push('\n');
         }
-        if !splitter_row.is_empty() {
-            splitters.push(splitter_row);
+    }
+
+    Ok(ret)
+}
+
+fn count_splits(setup: &Setup) -> usize {
+    let mut ret = 0;
+    let mut beams = vec![false; setup.width];
+
+    for row in &setup.grid {
+        ret += beams
+            .iter()
+            .zip(row)
+            .filter(|&(&beam, element)| beam && element.is_splitter())
+            .count();
+        beams = propagate(&beams, row).expect("beam presence cannot overflow");
+    }
+
+    ret
+}
+
+fn parse_input<R: BufRead>(reader: R) -> Result<Setup> {
+    let grid = parse::char_grid(reader, Element::from_char)?;
+
+    if !grid.iter().flatten().any(|&element| element == Element::Source) {
+        return Err(anyhow!("No start position found"));
+    }
+    let width = grid.first().map(|row| row.len()).ok_or_else(|| anyhow!("No input lines found"))?;
+
+    Ok(Setup { width, grid })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_timelines() -> Result<()> {
+        let setup = parse_input(TEST.as_bytes())?;
+        let timelines = trace_timelines(&setup)?;
+        assert_eq!(timelines.len(), 16);
+        assert_eq!(timelines[2][6..9], [1, 0, 1]);
+        assert_eq!(
+            timelines.last().unwrap().iter().sum::<usize>(),
+            count_paths::<usize>(&setup)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_paths_overflow() -> Result<()> {
+        // Every splitter row doubles the number of timelines
+        let mut input = String::from(".S.\n");
+        for _ in 0..80 {
+            input.push_str(".^.\n^.^\n");
         }
+        let setup = parse_input(input.as_bytes())?;
+        assert_eq!(count_paths::<u128>(&setup)?, 1 << 80);
+
+        let err = count_paths::<usize>(&setup).unwrap_err();
+        assert_eq!(err.to_string(), "Timeline count overflow in row 129");
+        Ok(())
     }
 
-    let start = start.ok_or_else(|| anyhow!("No start position found"))?;
-    let width = width.ok_or_else(|| anyhow!("No input lines found"))?;
+    #[test]
+    fn test_elements() -> Result<()> {
+        let input = "\
+S...S.
+......
+^...>.
+......
+.#...<
+......
+";
+        let setup = parse_input(input.as_bytes())?;
+        assert_eq!(count_splits(&setup), 1);
+        assert_eq!(count_paths::<usize>(&setup)?, 1);
+        assert_eq!(
+            render_text(&setup)?,
+            "\
+S...S.      2
+|...|.      2
+^|..>|      2
+.|...|      2
+.#..|<      1
+....|.      1
+Timelines per column: 4:1
+"
+        );
+        Ok(())
+    }
 
-    Ok(Setup {
-        start,
-        width,
-        splitters,
-    })
+    #[test]
+    fn test_render_text() -> Result<()> {
+        let setup = parse_input(".S.\n...\n.^.\n...\n".as_bytes())?;
+        assert_eq!(
+            render_text(&setup)?,
+            "\
+.S.      1
+.|.      1
+|^|      2
+|.|      2
+Timelines per column: 0:1 2:1
+"
+        );
+        Ok(())
+    }
 }
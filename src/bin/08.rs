@@ -2,7 +2,9 @@ use adv_code_2025::*;
 use anyhow::*;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use std::collections::HashSet;
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -38,18 +40,45 @@ fn main() -> Result<()> {
     //region Part 1
     println!("=== Part 1 ===");
 
-    fn part1<R: BufRead>(reader: R, n: usize) -> Result<usize> {
+    fn part1<R: BufRead>(reader: R, n: usize, top: usize) -> Result<usize> {
+        if top == 0 {
+            return Err(anyhow!(
+                "Need at least one circuit to multiply, got --top 0"
+            ));
+        }
         let coordinates = parse_input(reader)?;
-        let distances = calc_distances(&coordinates);
-        let group_sizes = build_groups_from_first_n(coordinates.len(), &distances, n);
-        Ok(group_sizes[0] * group_sizes[1] * group_sizes[2])
+        let pairs = ClosestPairs::new(&coordinates);
+        let group_sizes = build_groups_from_first_n(coordinates.len(), pairs, n);
+        if group_sizes.len() < top {
+            return Err(anyhow!(
+                "Only {} circuit(s) after {} connections, cannot multiply the {} largest",
+                group_sizes.len(),
+                n,
+                top
+            ));
+        }
+        Ok(group_sizes[..top].iter().product())
     }
 
-    assert_eq!(40, part1(BufReader::new(TEST.as_bytes()), 10)?);
+    assert_eq!(40, part1(BufReader::new(TEST.as_bytes()), 10, 3)?);
 
+    let connections = cli_value("connections", 1000)?;
+    let top = cli_value("top", 3)?;
     let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part1(input_file, 1000)?);
+    let result = time_snippet!(part1(input_file, connections, top)?);
     println!("Result = {}", result);
+
+    if cli_flag("histogram") {
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let coordinates = parse_input(input_file)?;
+        let pairs = ClosestPairs::new(&coordinates);
+        let group_sizes = build_groups_from_first_n(coordinates.len(), pairs, connections);
+
+        println!("\nCircuit sizes after {} connections:", connections);
+        for (size, count) in size_histogram(&group_sizes) {
+            println!("{:>6}: {}", size, count);
+        }
+    }
     //endregion
 
     //region Part 2
@@ -57,8 +86,8 @@ fn main() -> Result<()> {
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
         let coordinates = parse_input(reader)?;
-        let distances = calc_distances(&coordinates);
-        let (coord_a, coord_b) = unite_all_groups(&coordinates, &distances)?;
+        let pairs = ClosestPairs::new(&coordinates);
+        let (coord_a, coord_b) = unite_all_groups(&coordinates, pairs)?;
         Ok(coord_a.0 as usize * coord_b.0 as usize)
     }
 
@@ -69,93 +98,348 @@ fn main() -> Result<()> {
     println!("Result = {}", result);
     //endregion
 
+    //region Minimum spanning tree export
+    let dot_path = cli_option("mst-dot");
+    let csv_path = cli_option("mst-csv");
+    if dot_path.is_some() || csv_path.is_some() {
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let coordinates = parse_input(input_file)?;
+        let mst = minimum_spanning_tree(coordinates.len(), ClosestPairs::new(&coordinates));
+
+        if let Some(path) = dot_path {
+            std::fs::write(&path, mst_to_dot(&coordinates, &mst))?;
+            println!("\nMinimum spanning tree written to {}", path);
+        }
+        if let Some(path) = csv_path {
+            std::fs::write(&path, mst_to_csv(&coordinates, &mst))?;
+            println!("\nMinimum spanning tree written to {}", path);
+        }
+    }
+    //endregion
+
     Ok(())
 }
 
 type Coordinate = (i64, i64, i64);
+/// Squared distance and the indices `a < b` of two junction boxes
+type Pair = (i64, usize, usize);
 
-fn unite_all_groups(
-    coords: &Vec<Coordinate>,
-    distances: &Vec<(i64, usize, usize)>,
-) -> Result<(Coordinate, Coordinate)> {
-    let num_coords = coords.len();
-    let mut groups = init_groups(num_coords);
+/// Edge of the minimum spanning tree, in the order Kruskal's algorithm added it.
+#[derive(Debug, Clone, PartialEq)]
+struct MstEdge {
+    a: usize,
+    b: usize,
+    square_distance: i64,
+    /// Size of the circuit created by this connection
+    circuit_size: usize,
+}
+
+/// Union-find over junction box indices with union by size.
+struct Circuits {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    count: usize,
+}
+
+impl Circuits {
+    fn new(num_coords: usize) -> Self {
+        Circuits {
+            parent: (0..num_coords).collect(),
+            size: vec![1; num_coords],
+            count: num_coords,
+        }
+    }
 
-    for (_dist, a, b) in distances {
-        merge(&mut groups, *a, *b);
-        if groups.len() == 1 {
-            let coord_a = coords[*a];
-            let coord_b = coords[*b];
-            return Ok((coord_a, coord_b));
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
         }
+        i
     }
 
-    Err(anyhow!("Cannot unite all groups"))
+    /// Connects the circuits of `a` and `b`, returning the size of the merged
+    /// circuit, or `None` if both already were in the same circuit.
+    fn merge(&mut self, a: usize, b: usize) -> Option<usize> {
+        let (mut root_a, mut root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return None;
+        }
+        if self.size[root_a] < self.size[root_b] {
+            std::mem::swap(&mut root_a, &mut root_b);
+        }
+        self.parent[root_b] = root_a;
+        self.size[root_a] += self.size[root_b];
+        self.count -= 1;
+        Some(self.size[root_a])
+    }
+
+    /// Circuit sizes in descending order.
+    fn sizes(&self) -> Vec<usize> {
+        let mut sizes = (0..self.parent.len())
+            .filter(|&i| self.parent[i] == i)
+            .map(|i| self.size[i])
+            .collect::<Vec<usize>>();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        sizes
+    }
+}
+
+/// Kruskal's algorithm over the pairs in increasing distance, stopping as
+/// soon as all junction boxes form a single circuit.
+fn minimum_spanning_tree(num_coords: usize, pairs: impl Iterator<Item = Pair>) -> Vec<MstEdge> {
+    let mut circuits = Circuits::new(num_coords);
+    let mut edges = Vec::with_capacity(num_coords.saturating_sub(1));
+
+    for (square_distance, a, b) in pairs {
+        if circuits.count <= 1 {
+            break;
+        }
+        if let Some(circuit_size) = circuits.merge(a, b) {
+            edges.push(MstEdge {
+                a,
+                b,
+                square_distance,
+                circuit_size,
+            });
+        }
+    }
+
+    edges
+}
+
+fn unite_all_groups(
+    coords: &[Coordinate],
+    pairs: impl Iterator<Item = Pair>,
+) -> Result<(Coordinate, Coordinate)> {
+    let mst = minimum_spanning_tree(coords.len(), pairs);
+
+    match mst.last() {
+        Some(edge) if mst.len() + 1 == coords.len() => Ok((coords[edge.a], coords[edge.b])),
+        _ => Err(anyhow!("Cannot unite all groups")),
+    }
 }
 
 fn build_groups_from_first_n(
     num_coords: usize,
-    distances: &Vec<(i64, usize, usize)>,
+    pairs: impl Iterator<Item = Pair>,
     n: usize,
 ) -> Vec<usize> {
-    let mut groups = init_groups(num_coords);
+    let mut circuits = Circuits::new(num_coords);
 
-    let mut cnt = 0;
-    for (_dist, a, b) in distances {
-        if cnt == n {
-            break;
-        }
-        cnt += 1;
-        merge(&mut groups, *a, *b);
+    for (_dist, a, b) in pairs.take(n) {
+        circuits.merge(a, b);
     }
 
-    let mut group_sizes = groups.iter().map(|g| g.len()).collect::<Vec<usize>>();
-    group_sizes.sort();
-    group_sizes.reverse();
+    circuits.sizes()
+}
 
+/// Number of circuits per size, largest size first.
+fn size_histogram(group_sizes: &[usize]) -> Vec<(usize, usize)> {
     group_sizes
+        .iter()
+        .chunk_by(|&&size| size)
+        .into_iter()
+        .map(|(size, group)| (size, group.count()))
+        .collect()
 }
 
-fn merge(groups: &mut Vec<HashSet<usize>>, a: usize, b: usize) {
-    let idx_a = groups
-        .iter()
-        .position(|g| g.contains(&a))
-        .expect("Group for a not found");
-    let idx_b = groups
-        .iter()
-        .position(|g| g.contains(&b))
-        .expect("Group for b not found");
+fn mst_to_dot(coords: &[Coordinate], mst: &[MstEdge]) -> String {
+    let mut ret = String::from("graph mst {\n    node [shape=point];\n");
+    for (i, (x, y, z)) in coords.iter().enumerate() {
+        ret.push_str(&format!(
+            "    {} [xlabel=\"{}: {},{},{}\"];\n",
+            i, i, x, y, z
+        ));
+    }
+    for (order, edge) in mst.iter().enumerate() {
+        ret.push_str(&format!(
+            "    {} -- {} [label=\"#{} d={:.1}\"];\n",
+            edge.a,
+            edge.b,
+            order + 1,
+            (edge.square_distance as f64).sqrt()
+        ));
+    }
+    ret.push_str("}\n");
+    ret
+}
 
-    if idx_a != idx_b {
-        let group_a = groups.remove(idx_a);
-        let mut group_b = groups.remove(if idx_b > idx_a { idx_b - 1 } else { idx_b });
-        for item in group_a {
-            group_b.insert(item);
+fn mst_to_csv(coords: &[Coordinate], mst: &[MstEdge]) -> String {
+    let mut ret = String::from("order,a,b,ax,ay,az,bx,by,bz,square_distance,circuit_size\n");
+    for (order, edge) in mst.iter().enumerate() {
+        let (ax, ay, az) = coords[edge.a];
+        let (bx, by, bz) = coords[edge.b];
+        ret.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{}\n",
+            order + 1,
+            edge.a,
+            edge.b,
+            ax,
+            ay,
+            az,
+            bx,
+            by,
+            bz,
+            edge.square_distance,
+            edge.circuit_size
+        ));
+    }
+    ret
+}
+
+/// Static 3D k-d tree over the junction boxes, stored as a permutation of
+/// their indices: the median of each subrange is the node splitting it.
+struct KdTree<'a> {
+    coords: &'a [Coordinate],
+    nodes: Vec<usize>,
+}
+
+impl<'a> KdTree<'a> {
+    fn new(coords: &'a [Coordinate]) -> Self {
+        let mut nodes: Vec<usize> = (0..coords.len()).collect();
+        Self::build(coords, &mut nodes, 0);
+        KdTree { coords, nodes }
+    }
+
+    fn build(coords: &[Coordinate], nodes: &mut [usize], depth: usize) {
+        if nodes.len() <= 1 {
+            return;
         }
-        groups.push(group_b);
+        let mid = nodes.len() / 2;
+        nodes.select_nth_unstable_by_key(mid, |&i| axis_value(&coords[i], depth));
+        let (left, right) = nodes.split_at_mut(mid);
+        Self::build(coords, left, depth + 1);
+        Self::build(coords, &mut right[1..], depth + 1);
+    }
+
+    /// The `k` nearest boxes to box `from` among those with a larger index,
+    /// ordered by (distance, index).
+    fn nearest_above(&self, from: usize, k: usize) -> Vec<(i64, usize)> {
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        self.search(&self.nodes, 0, from, k, &mut best);
+        best.into_sorted_vec()
+    }
+
+    fn search(
+        &self,
+        nodes: &[usize],
+        depth: usize,
+        from: usize,
+        k: usize,
+        best: &mut BinaryHeap<(i64, usize)>,
+    ) {
+        if nodes.is_empty() || k == 0 {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        let node = nodes[mid];
+        let origin = &self.coords[from];
+
+        if node > from {
+            best.push((square_distance(origin, &self.coords[node]), node));
+            if best.len() > k {
+                best.pop();
+            }
+        }
+
+        let delta = axis_value(origin, depth) - axis_value(&self.coords[node], depth);
+        let (near, far) = if delta < 0 {
+            (&nodes[..mid], &nodes[mid + 1..])
+        } else {
+            (&nodes[mid + 1..], &nodes[..mid])
+        };
+        self.search(near, depth + 1, from, k, best);
+        // The far side can only hold closer boxes if the splitting plane is close enough
+        if best.len() < k || delta * delta <= best.peek().unwrap().0 {
+            self.search(far, depth + 1, from, k, best);
+        }
+    }
+}
+
+fn axis_value(coord: &Coordinate, depth: usize) -> i64 {
+    match depth % 3 {
+        0 => coord.0,
+        1 => coord.1,
+        _ => coord.2,
     }
 }
 
-fn init_groups(num_coords: usize) -> Vec<HashSet<usize>> {
-    (0..num_coords)
-        .map(|i| {
-            let mut hs = HashSet::new();
-            hs.insert(i);
-            hs
-        })
-        .collect::<Vec<HashSet<usize>>>()
+/// Neighbors of a single box (with larger indices) in increasing distance,
+/// fetched from the k-d tree in batches of doubling size.
+struct NeighborStream {
+    from: usize,
+    k: usize,
+    buffer: Vec<(i64, usize)>,
+    pos: usize,
 }
 
-fn calc_distances(coords: &Vec<Coordinate>) -> Vec<(i64, usize, usize)> {
-    let mut ret = vec![];
-    for i in 0..coords.len() {
-        for j in (i + 1)..coords.len() {
-            let dist = square_distance(&coords[i], &coords[j]);
-            ret.push((dist, i, j));
+impl NeighborStream {
+    fn next(&mut self, tree: &KdTree) -> Option<(i64, usize)> {
+        if self.pos == self.buffer.len() {
+            if self.buffer.len() < self.k {
+                // The last query already returned every remaining neighbor
+                return None;
+            }
+            self.k *= 2;
+            self.buffer = tree.nearest_above(self.from, self.k);
+        }
+        let ret = self.buffer.get(self.pos).copied();
+        self.pos += 1;
+        ret
+    }
+}
+
+/// Lazily yields all pairs of junction boxes ordered by (squared distance,
+/// a, b), without materializing the n·(n−1)/2 pairs. A heap holds the next
+/// candidate of every box's neighbor stream.
+struct ClosestPairs<'a> {
+    tree: KdTree<'a>,
+    streams: Vec<NeighborStream>,
+    heap: BinaryHeap<Reverse<Pair>>,
+}
+
+impl<'a> ClosestPairs<'a> {
+    const INITIAL_K: usize = 4;
+
+    fn new(coords: &'a [Coordinate]) -> Self {
+        let tree = KdTree::new(coords);
+        let mut streams = Vec::with_capacity(coords.len());
+        let mut heap = BinaryHeap::with_capacity(coords.len());
+
+        for from in 0..coords.len() {
+            let buffer = tree.nearest_above(from, Self::INITIAL_K);
+            let mut stream = NeighborStream {
+                from,
+                k: Self::INITIAL_K,
+                buffer,
+                pos: 0,
+            };
+            if let Some((dist, to)) = stream.next(&tree) {
+                heap.push(Reverse((dist, from, to)));
+            }
+            streams.push(stream);
+        }
+
+        ClosestPairs {
+            tree,
+            streams,
+            heap,
         }
     }
-    ret.sort();
-    ret
+}
+
+impl Iterator for ClosestPairs<'_> {
+    type Item = Pair;
+
+    fn next(&mut self) -> Option<Pair> {
+        let Reverse(pair) = self.heap.pop()?;
+        let from = pair.1;
+        if let Some((dist, to)) = self.streams[from].next(&self.tree) {
+            self.heap.push(Reverse((dist, from, to)));
+        }
+        Some(pair)
+    }
 }
 
 fn square_distance(a: &Coordinate, b: &Coordinate) -> i64 {
@@ -168,17 +452,89 @@ fn square_distance(a: &Coordinate, b: &Coordinate) -> i64 {
 fn parse_input<R: BufRead>(reader: R) -> Result<Vec<Coordinate>> {
     let mut ret = vec![];
 
-    for line in reader.lines() {
-        let line = line?;
-        let parts: Vec<&str> = line.trim().split(',').collect();
-        if parts.len() != 3 {
-            return Err(anyhow!("Invalid line: {}", line));
-        }
-        let x: i64 = parts[0].parse()?;
-        let y: i64 = parts[1].parse()?;
-        let z: i64 = parts[2].parse()?;
+    for line in parse::numbered_lines(reader) {
+        let (line_no, line) = line?;
+        let [x, y, z] = parse::parse_csv_line(&line, line_no)?;
         ret.push((x, y, z));
     }
 
     Ok(ret)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_pairs_sorted(coords: &[Coordinate]) -> Vec<Pair> {
+        let mut ret = vec![];
+        for i in 0..coords.len() {
+            for j in (i + 1)..coords.len() {
+                ret.push((square_distance(&coords[i], &coords[j]), i, j));
+            }
+        }
+        ret.sort();
+        ret
+    }
+
+    #[test]
+    fn test_closest_pairs_match_brute_force() -> Result<()> {
+        let coords = parse_input(TEST.as_bytes())?;
+        assert_eq!(
+            ClosestPairs::new(&coords).collect::<Vec<_>>(),
+            all_pairs_sorted(&coords)
+        );
+
+        // Pseudo-random boxes on a small grid, so there are many ties
+        let mut seed: u64 = 42;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % 20) as i64
+        };
+        let coords: Vec<Coordinate> = (0..150).map(|_| (next(), next(), next())).collect();
+        assert_eq!(
+            ClosestPairs::new(&coords).collect::<Vec<_>>(),
+            all_pairs_sorted(&coords)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_sizes() -> Result<()> {
+        let coords = parse_input(TEST.as_bytes())?;
+        let group_sizes = build_groups_from_first_n(coords.len(), ClosestPairs::new(&coords), 10);
+        assert_eq!(
+            size_histogram(&group_sizes),
+            vec![(5, 1), (4, 1), (2, 2), (1, 7)]
+        );
+
+        let group_sizes = build_groups_from_first_n(coords.len(), ClosestPairs::new(&coords), 1000);
+        assert_eq!(group_sizes, vec![20]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_minimum_spanning_tree() -> Result<()> {
+        let coords = parse_input(TEST.as_bytes())?;
+        let mst = minimum_spanning_tree(coords.len(), ClosestPairs::new(&coords));
+        assert_eq!(mst.len(), coords.len() - 1);
+        assert_eq!(
+            mst[0],
+            MstEdge {
+                a: 0,
+                b: 19,
+                square_distance: 100427,
+                circuit_size: 2
+            }
+        );
+        assert_eq!(mst.last().unwrap().circuit_size, coords.len());
+
+        let csv = mst_to_csv(&coords, &mst);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("1,0,19,162,817,812,425,690,689,100427,2")
+        );
+        Ok(())
+    }
+}
@@ -1,9 +1,12 @@
 use anyhow::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use adv_code_2025::*;
+use itertools::Itertools;
 
 const DAY: &str = "09";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -27,8 +30,8 @@ fn main() -> Result<()> {
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
         let tiles = parse_input(reader)?;
-        let answer = determine_max_area(&tiles) as usize;
-        Ok(answer)
+        let (a, b) = largest_rectangle(&tiles)?;
+        Ok(area(&a, &b) as usize)
     }
 
     assert_eq!(50, part1(BufReader::new(TEST.as_bytes()))?);
@@ -42,22 +45,9 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let tiles = parse_input(reader)?;
-        let edges = calculate_edges(&tiles);
-        let mut max_area: Option<i64> = None;
-
-        for a in &tiles {
-            for b in &tiles {
-                if is_valid_area(a, b, &edges) {
-                    let area = area(a, b);
-                    if max_area.is_none() || area > max_area.unwrap() {
-                        max_area = Some(area);
-                    }
-                }
-            }
-        }
-
-        Ok(max_area.unwrap() as usize)
+        let polygon = parse_polygon(reader)?;
+        let (a, b) = largest_filled_rectangle(&polygon)?;
+        Ok(area(&a, &b) as usize)
     }
 
     assert_eq!(24, part2(BufReader::new(TEST.as_bytes()))?);
@@ -67,10 +57,63 @@ fn main() -> Result<()> {
     println!("Result = {}", result);
     //endregion
 
+    //region Polygon info and rendering
+    if cli_flag("info") || cli_option("tile").is_some() || cli_option("top").is_some() {
+        println!("\n=== Polygon ===");
+
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let polygon = parse_polygon(input_file)?;
+        println!(
+            "{} vertices, {:?}, {} red/green tiles",
+            polygon.vertices.len(),
+            polygon.winding(),
+            polygon.tile_count()
+        );
+
+        if cli_flag("info") {
+            println!("{} valid rectangles", filled_rectangles(&polygon).count());
+        }
+
+        if cli_option("top").is_some() {
+            let k = cli_value("top", 5)?;
+            let print = |title: &str, top: Vec<Candidate>| {
+                println!("{}:", title);
+                for (rank, c) in top.iter().enumerate() {
+                    let ((ax, ay), (bx, by)) = c.corners;
+                    println!("#{} {} tiles, {},{} to {},{}", rank + 1, c.area, ax, ay, bx, by);
+                }
+            };
+            print("Largest rectangles", top_rectangles(&polygon.vertices, k));
+            print("Largest valid rectangles", top_filled_rectangles(&polygon, k));
+        }
+
+        if let Some(tile) = cli_option("tile") {
+            let pos = parse_position(&tile, 1).context("Invalid --tile")?;
+            let status = if polygon.contains_point(&pos) {
+                "red/green"
+            } else {
+                "outside"
+            };
+            println!("Tile {},{} is {}", pos.0, pos.1, status);
+        }
+    }
+
+    if let Some(path) = cli_option("svg") {
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let polygon = parse_polygon(input_file)?;
+        let best = largest_rectangle(&polygon.vertices)?;
+        let best_filled = largest_filled_rectangle(&polygon)?;
+        std::fs::write(&path, render_svg(&polygon, best, best_filled, 800.0))?;
+        println!("\nTile floor written to {}", path);
+    }
+    //endregion
+
     Ok(())
 }
 
 type Position = (i64, i64);
+/// Rectangle given by two opposite corner tiles
+type Rectangle = (Position, Position);
 
 #[derive(Debug)]
 enum Orientation {
@@ -86,46 +129,198 @@ struct Edge {
     end: i64,
 }
 
-fn is_valid_area(
-    a: &Position,
-    b: &Position,
-    edges: &Vec<Edge>,
-) -> bool {
-    let left = if a.0 < b.0 { a.0 } else { b.0 };
-    let right = if a.0 > b.0 { a.0 } else { b.0 };
-    let top = if a.1 < b.1 { a.1 } else { b.1 };
-    let bottom = if a.1 > b.1 { a.1 } else { b.1 };
-
-    // Check that no edge crosses the rectangle defined by (left, top) and (right, bottom)
-    for edge in edges {
-        match edge.orientation {
-            Orientation::Horizontal => {
-                // Horizontal edge at y = edge.position
-                if edge.position > top && edge.position < bottom {
-                    // Edge is within vertical bounds of rectangle
-                    if edge.start <= right && edge.end >= left {
-                        // Edge crosses the rectangle
-                        return false;
-                    }
-                }
-            }
-            Orientation::Vertical => {
-                // Vertical edge at x = edge.position
-                if edge.position > left && edge.position < right {
-                    // Edge is within horizontal bounds of rectangle
-                    if edge.start <= top && edge.end >= bottom {
-                        // Edge crosses the rectangle
-                        return false;
-                    }
+impl Edge {
+    fn contains(&self, pos: &Position) -> bool {
+        let (along, across) = match self.orientation {
+            Orientation::Horizontal => (pos.0, pos.1),
+            Orientation::Vertical => (pos.1, pos.0),
+        };
+        across == self.position && self.start <= along && along <= self.end
+    }
+
+    /// True if a ray cast from `pos` towards negative x crosses this edge.
+    /// The vertical extent is half-open, so a ray through a vertex is counted once.
+    fn is_crossed_by_ray_from(&self, pos: &Position) -> bool {
+        matches!(self.orientation, Orientation::Vertical)
+            && self.position < pos.0
+            && self.start <= pos.1
+            && pos.1 < self.end
+    }
+}
+
+/// Direction in which the vertices run around the polygon, with y growing
+/// downwards as in the puzzle's tile grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Closed rectilinear polygon through the red tiles in input order. Its
+/// boundary and interior are the red and green tiles.
+#[derive(Debug)]
+struct Polygon {
+    vertices: Vec<Position>,
+    edges: Vec<Edge>,
+}
+
+impl Polygon {
+    fn new(vertices: Vec<Position>) -> Self {
+        let edges = calculate_edges(&vertices);
+        Polygon { vertices, edges }
+    }
+
+    /// Twice the signed area enclosed by the vertex path (shoelace formula).
+    fn doubled_signed_area(&self) -> i64 {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+            .sum()
+    }
+
+    fn winding(&self) -> Winding {
+        // With y pointing down, a positive shoelace sum runs clockwise on screen
+        if self.doubled_signed_area() > 0 {
+            Winding::Clockwise
+        } else {
+            Winding::CounterClockwise
+        }
+    }
+
+    /// Number of red and green tiles, by Pick's theorem: the polygon's area
+    /// counts the interior tiles plus half of the boundary, minus one.
+    fn tile_count(&self) -> i64 {
+        let boundary: i64 = self.edges.iter().map(|e| e.end - e.start).sum();
+        (self.doubled_signed_area().abs() + boundary) / 2 + 1
+    }
+
+    /// True if the tile at `pos` is red or green: on the boundary, or inside
+    /// by the parity of the edges crossed by a ray cast to the left.
+    fn contains_point(&self, pos: &Position) -> bool {
+        self.edges.iter().any(|e| e.contains(pos))
+            || self.edges.iter().filter(|e| e.is_crossed_by_ray_from(pos)).count() % 2 == 1
+    }
+}
+
+/// Coordinate-compressed tile floor. Along each axis the tiles are grouped
+/// into blocks: one block per distinct vertex coordinate and one for every
+/// gap between consecutive coordinates. All tiles of a block cell are either
+/// red/green or not, so a rectangle is valid iff none of the cells it covers
+/// is outside, which a 2D prefix sum over the cells answers in O(1).
+struct CompressedFloor {
+    /// First coordinate of every block along x
+    xs: Vec<i64>,
+    /// First coordinate of every block along y
+    ys: Vec<i64>,
+    /// Row-major prefix sums with a leading zero row and column: entry
+    /// `(y, x)` counts the outside cells in blocks `..y` × `..x`
+    outside: Vec<u32>,
+}
+
+impl CompressedFloor {
+    fn new(polygon: &Polygon) -> Self {
+        let xs = block_starts(polygon.vertices.iter().map(|t| t.0));
+        let ys = block_starts(polygon.vertices.iter().map(|t| t.1));
+        let edges = &polygon.edges;
+        let stride = xs.len() + 1;
+        let mut outside = vec![0u32; stride * (ys.len() + 1)];
+
+        let mut vertical = edges
+            .iter()
+            .filter(|e| matches!(e.orientation, Orientation::Vertical))
+            .collect::<Vec<_>>();
+        vertical.sort_unstable_by_key(|e| e.position);
+        let mut horizontal = edges
+            .iter()
+            .filter(|e| matches!(e.orientation, Orientation::Horizontal))
+            .collect::<Vec<_>>();
+        horizontal.sort_unstable_by_key(|e| e.position);
+
+        for (by, &y) in ys.iter().enumerate() {
+            let filled = filled_intervals(&vertical, &horizontal, y);
+            let mut intervals = filled.iter().peekable();
+            let mut row_count = 0;
+            for (bx, &x) in xs.iter().enumerate() {
+                while intervals.next_if(|&&(_, end)| end < x).is_some() {}
+                if intervals.peek().is_none_or(|&&(start, _)| start > x) {
+                    row_count += 1;
                 }
+                outside[(by + 1) * stride + bx + 1] = outside[by * stride + bx + 1] + row_count;
             }
         }
+
+        CompressedFloor { xs, ys, outside }
+    }
+
+    /// Block cell containing the tile at `pos`.
+    fn block_of(&self, pos: &Position) -> (usize, usize) {
+        let bx = self.xs.partition_point(|&x| x <= pos.0) - 1;
+        let by = self.ys.partition_point(|&y| y <= pos.1) - 1;
+        (bx, by)
     }
 
-    true
+    /// True if all tiles of the rectangle spanned by the two block cells are red or green.
+    fn is_filled(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        let (left, right) = (a.0.min(b.0), a.0.max(b.0) + 1);
+        let (top, bottom) = (a.1.min(b.1), a.1.max(b.1) + 1);
+        let at = |y: usize, x: usize| self.outside[y * (self.xs.len() + 1) + x];
+        at(bottom, right) + at(top, left) - at(top, right) - at(bottom, left) == 0
+    }
 }
 
-fn calculate_edges(tiles: &Vec<Position>) -> Vec<Edge> {
+/// Sorted block starts for the given coordinates: each distinct value, plus
+/// the value after it if a gap follows before the next one.
+fn block_starts(coords: impl Iterator<Item = i64>) -> Vec<i64> {
+    let values = coords.sorted_unstable().dedup().collect::<Vec<_>>();
+    let mut ret = Vec::with_capacity(2 * values.len());
+    for (i, &value) in values.iter().enumerate() {
+        ret.push(value);
+        if i + 1 < values.len() && values[i + 1] > value + 1 {
+            ret.push(value + 1);
+        }
+    }
+    ret
+}
+
+/// Sorted, merged x intervals of the red/green tiles in row `y`: the
+/// interior found by casting a ray along the row, plus the edges on it.
+/// This is `Polygon::contains_point` for a whole row at once. Both edge lists
+/// must be sorted by position.
+fn filled_intervals(vertical: &[&Edge], horizontal: &[&Edge], y: i64) -> Vec<(i64, i64)> {
+    // Same half-open rule as `Edge::is_crossed_by_ray_from`
+    let crossings = vertical
+        .iter()
+        .filter(|e| e.start <= y && y < e.end)
+        .map(|e| e.position)
+        .collect::<Vec<_>>();
+
+    let mut intervals = crossings
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect::<Vec<_>>();
+    intervals.extend(
+        vertical
+            .iter()
+            .filter(|e| e.end == y)
+            .map(|e| (e.position, e.position)),
+    );
+    let from = horizontal.partition_point(|e| e.position < y);
+    let to = horizontal.partition_point(|e| e.position <= y);
+    intervals.extend(horizontal[from..to].iter().map(|e| (e.start, e.end)));
+    intervals.sort_unstable();
+
+    let mut merged: Vec<(i64, i64)> = Vec::with_capacity(intervals.len());
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn calculate_edges(tiles: &[Position]) -> Vec<Edge> {
     let mut edges = Vec::new();
     let n = tiles.len();
 
@@ -172,19 +367,116 @@ fn create_edge(
 }
 
 
-fn determine_max_area(tiles: &Vec<Position>) -> i64 {
-    let mut max_area: Option<i64> = None;
+/// Rectangle between two red tiles, ordered by area first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Candidate {
+    area: i64,
+    corners: Rectangle,
+}
+
+impl Candidate {
+    fn new(a: Position, b: Position) -> Self {
+        Candidate {
+            area: area(&a, &b),
+            corners: (a, b),
+        }
+    }
+}
+
+/// The `k` largest candidates seen so far, kept in a min-heap.
+struct TopCandidates {
+    k: usize,
+    heap: BinaryHeap<Reverse<Candidate>>,
+}
+
+impl TopCandidates {
+    fn new(k: usize) -> Self {
+        TopCandidates {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    /// True if a candidate of this area could still make it into the top `k`.
+    fn admits(&self, area: i64) -> bool {
+        self.k > 0 && (self.heap.len() < self.k || self.heap.peek().is_some_and(|min| area > min.0.area))
+    }
 
-    for tile in tiles {
-        for other_tile in tiles {
-            let area = area(tile, other_tile);
-            if max_area.is_none() || area > max_area.unwrap() {
-                max_area = Some(area);
+    fn push(&mut self, candidate: Candidate) {
+        self.heap.push(Reverse(candidate));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    /// Largest first.
+    fn into_sorted(self) -> Vec<Candidate> {
+        self.heap.into_sorted_vec().into_iter().map(|c| c.0).collect()
+    }
+}
+
+/// The `k` largest rectangles with two distinct red corners, ignoring the
+/// other tiles, largest first.
+fn top_rectangles(tiles: &[Position], k: usize) -> Vec<Candidate> {
+    let mut top = TopCandidates::new(k);
+    for (i, tile) in tiles.iter().enumerate() {
+        for other_tile in &tiles[(i + 1)..] {
+            if top.admits(area(tile, other_tile)) {
+                top.push(Candidate::new(*tile, *other_tile));
             }
         }
     }
+    top.into_sorted()
+}
+
+/// The `k` largest rectangles with red corners made of red and green tiles
+/// only, largest first.
+fn top_filled_rectangles(polygon: &Polygon, k: usize) -> Vec<Candidate> {
+    let tiles = &polygon.vertices;
+    let floor = CompressedFloor::new(polygon);
+    let blocks = tiles.iter().map(|t| floor.block_of(t)).collect::<Vec<_>>();
+    let mut top = TopCandidates::new(k);
+
+    for i in 0..tiles.len() {
+        for j in (i + 1)..tiles.len() {
+            // The O(1) lookup is still a cache miss, so skip rectangles that cannot win
+            if top.admits(area(&tiles[i], &tiles[j])) && floor.is_filled(blocks[i], blocks[j]) {
+                top.push(Candidate::new(tiles[i], tiles[j]));
+            }
+        }
+    }
+    top.into_sorted()
+}
+
+/// All pairs of distinct red tiles spanning a rectangle of red and green
+/// tiles only, in vertex order.
+fn filled_rectangles(polygon: &Polygon) -> impl Iterator<Item = Candidate> + '_ {
+    let tiles = &polygon.vertices;
+    let floor = CompressedFloor::new(polygon);
+    let blocks = tiles.iter().map(|t| floor.block_of(t)).collect::<Vec<_>>();
+
+    (0..tiles.len())
+        .flat_map(move |i| ((i + 1)..tiles.len()).map(move |j| (i, j)))
+        .filter(move |&(i, j)| floor.is_filled(blocks[i], blocks[j]))
+        .map(move |(i, j)| Candidate::new(tiles[i], tiles[j]))
+}
+
+/// Opposite red corners of the largest rectangle, ignoring the other tiles.
+/// A single red tile is a 1x1 rectangle on its own.
+fn largest_rectangle(tiles: &[Position]) -> Result<Rectangle> {
+    match tiles {
+        [] => Err(anyhow!("No red tiles found")),
+        [tile] => Ok((*tile, *tile)),
+        _ => Ok(top_rectangles(tiles, 1)[0].corners),
+    }
+}
 
-    max_area.unwrap()
+/// Opposite red corners of the largest rectangle made of red and green tiles only.
+fn largest_filled_rectangle(polygon: &Polygon) -> Result<Rectangle> {
+    top_filled_rectangles(polygon, 1)
+        .first()
+        .map(|c| c.corners)
+        .ok_or_else(|| anyhow!("No valid rectangle found"))
 }
 
 fn area(a: &Position, b: &Position) -> i64 {
@@ -202,23 +494,434 @@ fn area(a: &Position, b: &Position) -> i64 {
     width * height
 }
 
+/// Renders the tile floor as SVG, scaled so that its longer side is `size`
+/// pixels. Tiles are unit squares and the edges run through their centers;
+/// red tiles are marked with dots. The best part 1 rectangle is outlined in
+/// blue and the best part 2 rectangle is filled in green.
+fn render_svg(polygon: &Polygon, best: Rectangle, best_filled: Rectangle, size: f64) -> String {
+    let vertices = &polygon.vertices;
+    let min_x = vertices.iter().map(|v| v.0).min().unwrap_or(0);
+    let max_x = vertices.iter().map(|v| v.0).max().unwrap_or(0);
+    let min_y = vertices.iter().map(|v| v.1).min().unwrap_or(0);
+    let max_y = vertices.iter().map(|v| v.1).max().unwrap_or(0);
+    let scale = size / (max_x - min_x + 1).max(max_y - min_y + 1) as f64;
+    let px = |x: i64| (x - min_x) as f64 * scale;
+    let py = |y: i64| (y - min_y) as f64 * scale;
+    let stroke = (scale / 2.0).clamp(0.5, 4.0);
+
+    let mut ret = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\">\n",
+        px(max_x + 1),
+        py(max_y + 1)
+    );
+    ret.push_str("  <rect width=\"100%\" height=\"100%\" fill=\"#1e1e28\"/>\n");
+
+    let rect = |(a, b): Rectangle, style: &str| {
+        format!(
+            "  <rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>\n",
+            px(a.0.min(b.0)),
+            py(a.1.min(b.1)),
+            px(a.0.max(b.0) + 1) - px(a.0.min(b.0)),
+            py(a.1.max(b.1) + 1) - py(a.1.min(b.1)),
+            style
+        )
+    };
+    ret.push_str(&rect(best_filled, "fill=\"#3cb44b\" fill-opacity=\"0.6\""));
+
+    let half = scale / 2.0;
+    for edge in &polygon.edges {
+        let (x1, y1, x2, y2) = match edge.orientation {
+            Orientation::Horizontal => (edge.start, edge.position, edge.end, edge.position),
+            Orientation::Vertical => (edge.position, edge.start, edge.position, edge.end),
+        };
+        ret.push_str(&format!(
+            "  <line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"#e6e6e6\" stroke-width=\"{:.2}\"/>\n",
+            px(x1) + half,
+            py(y1) + half,
+            px(x2) + half,
+            py(y2) + half,
+            stroke
+        ));
+    }
+
+    ret.push_str(&rect(
+        best,
+        &format!("fill=\"none\" stroke=\"#4363d8\" stroke-width=\"{:.2}\"", 2.0 * stroke),
+    ));
+
+    for v in vertices {
+        ret.push_str(&format!(
+            "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"#e6194b\"/>\n",
+            px(v.0) + half,
+            py(v.1) + half,
+            stroke * 1.5
+        ));
+    }
+
+    ret.push_str("</svg>\n");
+    ret
+}
+
 fn parse_input<R: BufRead>(reader: R) -> Result<Vec<Position>> {
+    Ok(parse_numbered(reader)?
+        .into_iter()
+        .map(|(_, pos)| pos)
+        .collect())
+}
+
+/// Red tiles with the line numbers they were read from; blank lines are skipped.
+fn parse_numbered<R: BufRead>(reader: R) -> Result<Vec<(usize, Position)>> {
     let mut ret = Vec::new();
-    for line in reader.lines() {
-        let line = line?;
-        let mut parts = line.split(',');
-        let x = parts
-            .next()
-            .ok_or_else(|| anyhow!("Missing X part"))?
-            .trim()
-            .parse::<i64>()?;
-        let y = parts
-            .next()
-            .ok_or_else(|| anyhow!("Missing Y part"))?
-            .trim()
-            .parse::<i64>()?;
-        ret.push((x, y));
+    for line in parse::numbered_lines(reader) {
+        let (line_no, line) = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        ret.push((line_no, parse_position(&line, line_no)?));
     }
 
     Ok(ret)
-}
\ No newline at end of file
+}
+
+fn parse_position(s: &str, line_no: usize) -> Result<Position, parse::ParseError> {
+    let [x, y] = parse::parse_csv_line(s, line_no)?;
+    Result::Ok((x, y))
+}
+
+/// Parses the red tiles and checks that they form a simple rectilinear polygon.
+fn parse_polygon<R: BufRead>(reader: R) -> Result<Polygon> {
+    let tiles = parse_numbered(reader)?;
+    validate_polygon(&tiles)?;
+    Ok(Polygon::new(tiles.into_iter().map(|(_, pos)| pos).collect()))
+}
+
+/// Rejects vertex lists that do not describe a simple rectilinear polygon:
+/// too few vertices, duplicate vertices, consecutive vertices that are not
+/// axis-aligned, and edges that touch or cross anywhere but at their shared
+/// vertex. Errors name the input lines of the offending vertices.
+fn validate_polygon(tiles: &[(usize, Position)]) -> Result<()> {
+    let n = tiles.len();
+    if n < 4 {
+        return Err(anyhow!(
+            "Degenerate polygon: need at least 4 red tiles, got {}",
+            n
+        ));
+    }
+
+    let mut seen = HashMap::new();
+    for &(line, pos) in tiles {
+        if let Some(first) = seen.insert(pos, line) {
+            return Err(anyhow!(
+                "Line {}: duplicate red tile {},{} (first seen in line {})",
+                line,
+                pos.0,
+                pos.1,
+                first
+            ));
+        }
+    }
+
+    for i in 0..n {
+        let (line_a, a) = tiles[i];
+        let (line_b, b) = tiles[(i + 1) % n];
+        if a.0 != b.0 && a.1 != b.1 {
+            return Err(anyhow!(
+                "Line {}: red tile {},{} is not in the same row or column as {},{} in line {}",
+                line_b,
+                b.0,
+                b.1,
+                a.0,
+                a.1,
+                line_a
+            ));
+        }
+    }
+
+    // Edges as (left, top, right, bottom) boxes, swept in order of their left side
+    let bounds = |i: usize| {
+        let (a, b) = (tiles[i].1, tiles[(i + 1) % n].1);
+        (a.0.min(b.0), a.1.min(b.1), a.0.max(b.0), a.1.max(b.1))
+    };
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_unstable_by_key(|&i| bounds(i).0);
+
+    for (k, &i) in order.iter().enumerate() {
+        let box_i = bounds(i);
+        for &j in &order[k + 1..] {
+            let box_j = bounds(j);
+            if box_j.0 > box_i.2 {
+                break;
+            }
+            let overlap = (
+                box_i.0.max(box_j.0),
+                box_i.1.max(box_j.1),
+                box_i.2.min(box_j.2),
+                box_i.3.min(box_j.3),
+            );
+            if overlap.0 > overlap.2 || overlap.1 > overlap.3 {
+                continue;
+            }
+            // Consecutive edges must only share their common vertex
+            let adjacent = (i + 1) % n == j || (j + 1) % n == i;
+            if adjacent && overlap.0 == overlap.2 && overlap.1 == overlap.3 {
+                continue;
+            }
+            let (first, second) = (i.min(j), i.max(j));
+            return Err(anyhow!(
+                "Self-intersection: edge from line {} to line {} meets edge from line {} to line {} at {},{}",
+                tiles[first].0,
+                tiles[(first + 1) % n].0,
+                tiles[second].0,
+                tiles[(second + 1) % n].0,
+                overlap.0,
+                overlap.1
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Red/green tiles of the polygon, found independently of ray casting by
+    /// flood-filling the outside of the drawn boundary. Vertices must be
+    /// non-negative; the raster is indexed by `[y][x]`.
+    fn rasterize(polygon: &Polygon) -> Vec<Vec<bool>> {
+        // One tile of margin on every side, so the outside is connected
+        let width = polygon.vertices.iter().map(|v| v.0).max().unwrap() as usize + 3;
+        let height = polygon.vertices.iter().map(|v| v.1).max().unwrap() as usize + 3;
+        let mut boundary = vec![vec![false; width]; height];
+        for edge in &polygon.edges {
+            for along in edge.start..=edge.end {
+                let (x, y) = match edge.orientation {
+                    Orientation::Horizontal => (along, edge.position),
+                    Orientation::Vertical => (edge.position, along),
+                };
+                boundary[y as usize + 1][x as usize + 1] = true;
+            }
+        }
+
+        let mut outside = vec![vec![false; width]; height];
+        let mut todo = vec![(0, 0)];
+        while let Some((x, y)) = todo.pop() {
+            if boundary[y][x] || outside[y][x] {
+                continue;
+            }
+            outside[y][x] = true;
+            if x > 0 {
+                todo.push((x - 1, y));
+            }
+            if x + 1 < width {
+                todo.push((x + 1, y));
+            }
+            if y > 0 {
+                todo.push((x, y - 1));
+            }
+            if y + 1 < height {
+                todo.push((x, y + 1));
+            }
+        }
+
+        outside[1..height - 1]
+            .iter()
+            .map(|row| row[1..width - 1].iter().map(|&o| !o).collect())
+            .collect()
+    }
+
+    /// Rectilinear polygon tracing a staircase around a jagged circle.
+    fn staircase_polygon(seed: u64, n: usize, radius: f64) -> Polygon {
+        let mut seed = seed;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as f64 / (1u64 << 31) as f64
+        };
+        let points = (0..n)
+            .map(|k| {
+                let angle = 2.0 * std::f64::consts::PI * k as f64 / n as f64;
+                let r = radius * (0.5 + 0.5 * next());
+                (
+                    (radius + r * angle.cos()) as i64,
+                    (radius + r * angle.sin()) as i64,
+                )
+            })
+            .collect::<Vec<Position>>();
+
+        let mut vertices: Vec<Position> = vec![];
+        for k in 0..n {
+            let (a, b) = (points[k], points[(k + 1) % n]);
+            for v in [a, (b.0, a.1)] {
+                if vertices.last() != Some(&v) {
+                    vertices.push(v);
+                }
+            }
+        }
+        while vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+        // Drop vertices in the middle of a straight line
+        let mut i = 0;
+        while i < vertices.len() && vertices.len() > 4 {
+            let m = vertices.len();
+            let (a, b, c) = (vertices[(i + m - 1) % m], vertices[i], vertices[(i + 1) % m]);
+            if (a.0 == b.0 && b.0 == c.0) || (a.1 == b.1 && b.1 == c.1) {
+                vertices.remove(i);
+                i = 0;
+            } else {
+                i += 1;
+            }
+        }
+        Polygon::new(vertices)
+    }
+
+    #[test]
+    fn test_containment_matches_raster() {
+        for seed in 0..60 {
+            let polygon = staircase_polygon(seed, 6 + seed as usize % 20, 20.0);
+            let numbered = polygon.vertices.iter().copied().enumerate().collect::<Vec<_>>();
+            if validate_polygon(&numbered).is_err() {
+                continue;
+            }
+            let raster = rasterize(&polygon);
+            for (y, row) in raster.iter().enumerate() {
+                for (x, &filled) in row.iter().enumerate() {
+                    assert_eq!(polygon.contains_point(&(x as i64, y as i64)), filled);
+                }
+            }
+
+            let tiles = raster.iter().flatten().filter(|&&filled| filled).count();
+            assert_eq!(polygon.tile_count(), tiles as i64);
+
+            let floor = CompressedFloor::new(&polygon);
+            for a in &polygon.vertices {
+                for b in &polygon.vertices {
+                    let expected = (a.1.min(b.1)..=a.1.max(b.1)).all(|y| {
+                        (a.0.min(b.0)..=a.0.max(b.0)).all(|x| raster[y as usize][x as usize])
+                    });
+                    assert_eq!(floor.is_filled(floor.block_of(a), floor.block_of(b)), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_top_rectangles() {
+        for seed in 0..20 {
+            let polygon = staircase_polygon(seed, 6 + seed as usize % 12, 20.0);
+            let numbered = polygon.vertices.iter().copied().enumerate().collect::<Vec<_>>();
+            if validate_polygon(&numbered).is_err() {
+                continue;
+            }
+
+            let mut all = filled_rectangles(&polygon).collect::<Vec<_>>();
+            let raster = rasterize(&polygon);
+            for c in &all {
+                let (a, b) = c.corners;
+                assert!((a.1.min(b.1)..=a.1.max(b.1)).all(|y| {
+                    (a.0.min(b.0)..=a.0.max(b.0)).all(|x| raster[y as usize][x as usize])
+                }));
+            }
+
+            all.sort_unstable_by(|a, b| b.cmp(a));
+            for k in [0, 1, 3, all.len() + 1] {
+                let top = top_filled_rectangles(&polygon, k);
+                let areas = top.iter().map(|c| c.area).collect::<Vec<_>>();
+                let expected = all.iter().take(k).map(|c| c.area).collect::<Vec<_>>();
+                assert_eq!(areas, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_winding() -> Result<()> {
+        let polygon = Polygon::new(parse_input(TEST.as_bytes())?);
+        assert_eq!(polygon.winding(), Winding::Clockwise);
+
+        let reversed = polygon.vertices.iter().rev().copied().collect();
+        assert_eq!(Polygon::new(reversed).winding(), Winding::CounterClockwise);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_polygon() {
+        let error = |input: &str| parse_polygon(input.as_bytes()).unwrap_err().to_string();
+
+        assert_eq!(
+            error("0,0\n2,0\n2,2\n"),
+            "Degenerate polygon: need at least 4 red tiles, got 3"
+        );
+        assert_eq!(
+            error("0,0\n2,0\n2,2\n0,0\n0,2\n"),
+            "Line 4: duplicate red tile 0,0 (first seen in line 1)"
+        );
+        assert_eq!(
+            error("0,0\n2,0\n3,2\n0,2\n"),
+            "Line 3: red tile 3,2 is not in the same row or column as 2,0 in line 2"
+        );
+        // The edge along x = 2 cuts through the bottom edge
+        assert_eq!(
+            error("0,0\n4,0\n4,4\n2,4\n2,-2\n6,-2\n6,2\n0,2\n"),
+            "Self-intersection: edge from line 1 to line 2 meets edge from line 4 to line 5 at 2,0"
+        );
+        // Spike doubling back over the previous edge
+        assert_eq!(
+            error("0,0\n4,0\n2,0\n2,2\n0,2\n"),
+            "Self-intersection: edge from line 1 to line 2 meets edge from line 2 to line 3 at 2,0"
+        );
+        assert_eq!(
+            error("0,0\n2,0\nx,2\n0,2\n"),
+            "Line 3, column 1: invalid value 'x': invalid digit found in string"
+        );
+        assert!(parse_polygon(TEST.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_render_svg() -> Result<()> {
+        let polygon = parse_polygon(TEST.as_bytes())?;
+        // 10x7 tiles from 2,1 to 11,7, scaled to 80 pixels per tile
+        let svg = render_svg(&polygon, ((11, 1), (2, 5)), ((9, 5), (2, 3)), 800.0);
+        let lines = svg.lines().collect::<Vec<_>>();
+
+        assert!(lines[0].contains(r#"width="800" height="560""#));
+        assert!(lines.contains(
+            &r##"  <line x1="440.00" y1="40.00" x2="760.00" y2="40.00" stroke="#e6e6e6" stroke-width="4.00"/>"##
+        ));
+        assert!(lines.contains(
+            &r##"  <rect x="0.00" y="0.00" width="800.00" height="400.00" fill="none" stroke="#4363d8" stroke-width="8.00"/>"##
+        ));
+        assert!(lines.contains(
+            &r##"  <rect x="0.00" y="160.00" width="640.00" height="240.00" fill="#3cb44b" fill-opacity="0.6"/>"##
+        ));
+        assert_eq!(lines.iter().filter(|l| l.contains("<circle")).count(), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_largest_rectangle_edge_cases() -> Result<()> {
+        assert!(largest_rectangle(&[]).is_err());
+        assert_eq!(largest_rectangle(&[(3, 4)])?, ((3, 4), (3, 4)));
+
+        let tiles = parse_input(TEST.as_bytes())?;
+        let top = top_rectangles(&tiles, tiles.len() * tiles.len());
+        assert_eq!(top.len(), tiles.len() * (tiles.len() - 1) / 2);
+        assert!(top.iter().all(|c| c.corners.0 != c.corners.1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_concave_polygon() -> Result<()> {
+        // U shape: the notch between the arms at x = 3 is not red or green
+        let input = "0,0\n2,0\n2,4\n4,4\n4,0\n6,0\n6,6\n0,6\n";
+        let polygon = Polygon::new(parse_input(input.as_bytes())?);
+        let floor = CompressedFloor::new(&polygon);
+        let filled = |a: Position, b: Position| floor.is_filled(floor.block_of(&a), floor.block_of(&b));
+
+        assert!(filled((0, 0), (2, 6)));
+        assert!(filled((0, 4), (6, 6)));
+        assert!(!filled((0, 0), (6, 6)));
+        assert!(!filled((3, 0), (3, 3)));
+        Ok(())
+    }
+}
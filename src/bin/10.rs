@@ -1,4 +1,3 @@
-use std::collections::{HashSet, VecDeque};
 use anyhow::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -17,20 +16,14 @@ const TEST: &str = "\
 
 fn main() -> Result<()> {
     start_day(DAY);
+    parallel::init_thread_pool()?;
 
     //region Part 1
     println!("=== Part 1 ===");
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let mut answer = 0;
         let problems = parse_input(reader)?;
-
-        for problem in &problems {
-            let steps = solve_problem(problem)?;
-            answer += steps;
-        }
-
-        Ok(answer)
+        parallel::map_units(&problems, solve_problem).into_iter().sum()
     }
 
     assert_eq!(7, part1(BufReader::new(TEST.as_bytes()))?);
@@ -44,15 +37,8 @@ fn main() -> Result<()> {
     println!("\n=== Part 2 ===");
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let mut answer = 0;
         let problems = parse_input(reader)?;
-
-        for problem in &problems {
-            let steps = solve_problem2(problem)?;
-            answer += steps;
-        }
-
-        Ok(answer)
+        parallel::map_units(&problems, solve_problem2).into_iter().sum()
     }
 
     assert_eq!(33, part2(BufReader::new(TEST.as_bytes()))?);
@@ -72,38 +58,59 @@ struct Problem {
     joltages : Vec<usize>,
 }
 
-fn solve_problem2(problem: &Problem) -> Result<usize> {
-    let n = problem.joltages.len();
-    let start = vec![0; n];
-    let mut todo = VecDeque::new();
-    todo.push_back((start, 0));
-    let mut visited = HashSet::new();
-
-    while let Some((state, steps)) = todo.pop_front() {
-        if state == problem.joltages {
-            return Ok(steps);
+impl Problem {
+    /// Indicator lights to switch on, one bit per light.
+    fn goal_mask(&self) -> Result<u64> {
+        if self.goal.len() > u64::BITS as usize {
+            return Err(anyhow!("At most {} indicator lights are supported", u64::BITS));
         }
+        Ok(self
+            .goal
+            .iter()
+            .enumerate()
+            .filter(|&(_, &on)| on)
+            .fold(0, |mask, (pos, _)| mask | 1 << pos))
+    }
 
-        visited.insert(state_str2(&state));
+    /// Lights toggled by each button, one bit per light.
+    fn button_masks(&self) -> Vec<u64> {
+        let n = self.goal.len();
+        self.buttons
+            .iter()
+            .map(|button| {
+                button
+                    .iter()
+                    .filter(|&&pos| pos < n)
+                    .fold(0, |mask, &pos| mask ^ 1 << pos)
+            })
+            .collect()
+    }
+}
 
-        for button in &problem.buttons {
-            let mut new_state = state.clone();
-            for &pos in button {
-                if pos < n {
-                    new_state[pos] +=1;
+fn solve_problem2(problem: &Problem) -> Result<usize> {
+    let goal = &problem.joltages;
+    let n = goal.len();
+    let neighbors = |state: &Vec<usize>| {
+        problem
+            .buttons
+            .iter()
+            .filter_map(|button| {
+                let mut new_state = state.clone();
+                for &pos in button {
+                    if pos < n {
+                        new_state[pos] += 1;
+                    }
                 }
-            }
-            let new_state_str = state_str2(&new_state);
-            if !visited.contains(&new_state_str) && is_goal_reachable(&new_state, &problem.joltages) {
-                todo.push_back((new_state, steps + 1));
-            }
-        }
-    }
+                is_goal_reachable(&new_state, goal).then_some(new_state)
+            })
+            .collect::<Vec<_>>()
+    };
 
-    Err(anyhow!("No solution found"))
+    search::bfs(vec![0; n], neighbors, |state| state == goal)
+        .ok_or_else(|| anyhow!("No solution found"))
 }
 
-fn is_goal_reachable(state: &Vec<usize>, goal: &Vec<usize>) -> bool {
+fn is_goal_reachable(state: &[usize], goal: &[usize]) -> bool {
     for (s, g) in state.iter().zip(goal.iter()) {
         if s > g {
             return false;
@@ -112,93 +119,100 @@ fn is_goal_reachable(state: &Vec<usize>, goal: &Vec<usize>) -> bool {
     true
 }
 
-fn state_str2(state: &Vec<usize>) -> String {
-    state.iter().map(|&i| i.to_string()).collect::<Vec<String>>().join(",")
-}
-
 fn solve_problem(problem: &Problem) -> Result<usize> {
-    let n = problem.goal.len();
-    let start = vec![false; n];
-    let mut todo = VecDeque::new();
-    todo.push_back((start, 0));
-    let mut visited = HashSet::new();
-
-    while let Some((state, steps)) = todo.pop_front() {
-        if state == problem.goal {
-            return Ok(steps);
-        }
-
-        visited.insert(state_str(&state));
-
-        for button in &problem.buttons {
-            let mut new_state = state.clone();
-            for &pos in button {
-                if pos < n {
-                    new_state[pos] = !new_state[pos];
-                }
-            }
-            let new_state_str = state_str(&new_state);
-            if !visited.contains(&new_state_str) {
-                todo.push_back((new_state, steps + 1));
-            }
-        }
-    }
-
-    Err(anyhow!("No solution found"))
+    let goal = problem.goal_mask()?;
+    let buttons = problem.button_masks();
+
+    search::bfs(
+        0u64,
+        |&state| buttons.iter().map(move |&button| state ^ button),
+        |&state| state == goal,
+    )
+    .ok_or_else(|| anyhow!("No solution found"))
 }
 
-fn state_str(state: &Vec<bool>) -> String {
-    state.iter().map(|&b| if b { '#' } else { '.' }).collect()
-}
-
-
 fn parse_input<R: BufRead>(reader: R) -> Result<Vec<Problem>> {
     let mut problems = Vec::new();
-    for line in reader.lines() {
-        let line = line?;
-        let problem = parse_line(&line)?;
+    for line in parse::numbered_lines(reader) {
+        let (line_no, line) = line?;
+        let problem = parse_line(&line, line_no)?;
         problems.push(problem);
     }
     Ok(problems)
 }
 
-fn parse_line(line: &str) -> Result<Problem> {
-    let parts: Vec<&str> = line.split(' ').collect();
+/// Parses `[.##.] (3) (1,3) {3,5,4,7}`: the indicator lights, the buttons
+/// with the lights they toggle, and the joltage requirements.
+fn parse_line(line: &str, line_no: usize) -> Result<Problem, parse::ParseError> {
     let mut goal = Vec::new();
     let mut buttons = Vec::new();
     let mut joltages = Vec::new();
 
-    for part in parts {
-        if part.starts_with('[') {
-            for ch in part.chars().skip(1) {
-                match ch {
-                    '#' => goal.push(true),
-                    '.' => goal.push(false),
-                    ']' => break,
-                    _ => return Err(anyhow!("Invalid character in goal")),
+    for (column, part) in parse::split_columns(line, ' ', 1) {
+        let error = |message: String| parse::ParseError::new(line_no, column, message);
+        let inner = |open: char, close: char| {
+            part.strip_prefix(open)
+                .and_then(|part| part.strip_suffix(close))
+                .ok_or_else(|| error(format!("expected '{}...{}', got '{}'", open, close, part)))
+        };
+
+        match part.chars().next() {
+            Some('[') => {
+                for (x, ch) in inner('[', ']')?.chars().enumerate() {
+                    match ch {
+                        '#' => goal.push(true),
+                        '.' => goal.push(false),
+                        _ => {
+                            return Err(parse::ParseError::new(
+                                line_no,
+                                column + x + 1,
+                                format!("invalid indicator light '{}'", ch),
+                            ))
+                        }
+                    }
                 }
             }
+            Some('(') => buttons.push(parse::parse_list(inner('(', ')')?, ',', line_no, column + 1)?),
+            Some('{') => joltages = parse::parse_list(inner('{', '}')?, ',', line_no, column + 1)?,
+            _ => return Err(error(format!("unexpected '{}'", part))),
         }
+    }
 
-        if part.starts_with('(') {
-            let button_str = &part[1..part.len()-1];
-            let button = button_str
-                .split(',')
-                .map(|s| s.parse::<usize>())
-                .collect::<Result<Vec<usize>, _>>()?;
-            // Store buttons as needed
-            buttons.push(button);
-        }
+    Result::Ok(Problem { goal, buttons, joltages })
+}
 
-        if part.starts_with('{') {
-            let joltage_str = &part[1..part.len()-1];
-            joltages = joltage_str
-                .split(',')
-                .map(|s| s.parse::<usize>())
-                .collect::<Result<Vec<usize>, _>>()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_problem_matches_subsets() -> Result<()> {
+        // Pressing a button twice cancels out, so the best solution is a subset of the buttons
+        for problem in parse_input(TEST.as_bytes())? {
+            let (goal, buttons) = (problem.goal_mask()?, problem.button_masks());
+            let best = (0u32..1 << buttons.len())
+                .filter(|subset| {
+                    let lights = (0..buttons.len())
+                        .filter(|i| subset & 1 << i != 0)
+                        .fold(0, |mask, i| mask ^ buttons[i]);
+                    lights == goal
+                })
+                .map(|subset| subset.count_ones() as usize)
+                .min();
+            assert_eq!(Some(solve_problem(&problem)?), best);
         }
-
+        Ok(())
     }
 
-    Ok(Problem { goal, buttons, joltages })
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_errors() {
+        let error = |line: &str| parse_line(line, 1).unwrap_err().to_string();
+        assert_eq!(error("[.#x] (0) {1}"), "Line 1, column 4: invalid indicator light 'x'");
+        assert_eq!(
+            error("[.#] (0,x) {1}"),
+            "Line 1, column 9: invalid value 'x': invalid digit found in string"
+        );
+        assert_eq!(error("[.#] (0 {1}"), "Line 1, column 6: expected '(...)', got '(0'");
+        assert_eq!(error("[.#]  (0) {1}"), "Line 1, column 6: unexpected ''");
+    }
+}
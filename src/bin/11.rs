@@ -2,7 +2,8 @@ use adv_code_2025::*;
 use anyhow::*;
 use code_timing_macros::time_snippet;
 use const_format::concatcp;
-use std::collections::{HashMap, HashSet, VecDeque};
+use itertools::Itertools;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -46,7 +47,7 @@ fn main() -> Result<()> {
 
     fn part1<R: BufRead>(reader: R) -> Result<usize> {
         let graph = parse_input(reader)?;
-        let answer = count_paths("you", "out", &graph);
+        let answer = count_paths(&graph, graph.id("you")?, graph.id("out")?)?;
         Ok(answer)
     }
 
@@ -62,8 +63,11 @@ fn main() -> Result<()> {
 
     fn part2<R: BufRead>(reader: R) -> Result<usize> {
         let graph = parse_input(reader)?;
-        let mut cache: Cache = HashMap::new();
-        let answer = count_paths2("svr", "out", &graph, false, false, &mut cache);
+        let query = PathQuery {
+            required: vec![graph.id("dac")?, graph.id("fft")?],
+            ..PathQuery::new(graph.id("svr")?, graph.id("out")?)
+        };
+        let answer = count_query_paths(&graph, &query)?;
         Ok(answer)
     }
 
@@ -74,71 +78,845 @@ fn main() -> Result<()> {
     println!("Result = {}", result);
     //endregion
 
+    //region Path queries
+    if ["from", "to", "via", "avoid", "order"]
+        .iter()
+        .any(|name| cli_option(name).is_some())
+    {
+        println!("\n=== Path query ===");
+
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let graph = parse_input(input_file)?;
+        let query = PathQuery::from_cli(&graph)?;
+        println!("{} paths", count_query_paths(&graph, &query)?);
+    }
+
+    if let Some(path) = cli_option("dot") {
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let graph = parse_input(input_file)?;
+        std::fs::write(&path, graph_to_dot(&graph, &PathQuery::from_cli(&graph)?)?)?;
+        println!("\nDevice graph written to {}", path);
+    }
+
+    let enumerate = cli_option("paths").is_some();
+    let sample = cli_option("sample").is_some();
+    if enumerate || sample {
+        let input_file = BufReader::new(File::open(INPUT_FILE)?);
+        let graph = parse_input(input_file)?;
+        let end = graph.id("out")?;
+        let seed = match cli_option("seed") {
+            Some(_) => cli_value("seed", 0)?,
+            None => std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_nanos() as u64,
+        };
+        let mut rng = SplitMix64(seed);
+        let show = |path: &[NodeId]| path.iter().map(|&node| &graph.names[node]).join(" -> ");
+
+        for start in ["you", "svr"] {
+            let Result::Ok(start) = graph.id(start) else {
+                continue;
+            };
+            if enumerate {
+                let limit = cli_value("paths", 10)?;
+                println!("\n=== Paths from {} ===", graph.names[start]);
+                for path in Paths::new(&graph, start, end)?.take(limit) {
+                    println!("{}", show(&path));
+                }
+            }
+            if sample {
+                let count = cli_value("sample", 10)?;
+                let paths = paths_to(&graph, start, end)?;
+                println!(
+                    "\n=== Random paths from {} (seed {}) ===",
+                    graph.names[start], seed
+                );
+                for _ in 0..count {
+                    if let Some(path) = sample_path(&graph, &paths, start, end, &mut rng) {
+                        println!("{}", show(&path));
+                    }
+                }
+            }
+        }
+    }
+    //endregion
+
     Ok(())
 }
 
-type Cache = HashMap<(String, bool, bool), usize>;
+type NodeId = usize;
+
+/// Device graph with the device names interned to dense ids.
+#[derive(Debug, Default)]
+struct Graph {
+    names: Vec<String>,
+    ids: HashMap<String, NodeId>,
+    /// Outputs of every device, indexed by id
+    outputs: Vec<Vec<NodeId>>,
+}
+
+impl Graph {
+    /// Id of the device, adding it if it is new.
+    fn intern(&mut self, name: &str) -> NodeId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.outputs.push(vec![]);
+        id
+    }
+
+    fn id(&self, name: &str) -> Result<NodeId> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown device '{}'", name))
+    }
+
+    /// Devices reachable from `start`, each one before all of its outputs.
+    fn topological_order(&self, start: NodeId) -> Result<Vec<NodeId>> {
+        self.topological_order_from([start])
+    }
+
+    /// Devices reachable from any of `starts`, each one before all of its
+    /// outputs. Uses an explicit stack, so long chains cannot overflow the
+    /// call stack.
+    fn topological_order_from(
+        &self,
+        starts: impl IntoIterator<Item = NodeId>,
+    ) -> Result<Vec<NodeId>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            Open,
+            Done,
+        }
+
+        let mut state = vec![State::New; self.names.len()];
+        let mut post_order = Vec::new();
+        let mut stack = vec![];
+
+        for start in starts {
+            if state[start] != State::New {
+                continue;
+            }
+            state[start] = State::Open;
+            stack.push((start, 0));
+
+            while let Some((node, next)) = stack.last_mut() {
+                let node = *node;
+                let Some(&output) = self.outputs[node].get(*next) else {
+                    state[node] = State::Done;
+                    post_order.push(node);
+                    stack.pop();
+                    continue;
+                };
+                *next += 1;
+                match state[output] {
+                    State::New => {
+                        state[output] = State::Open;
+                        stack.push((output, 0));
+                    }
+                    State::Open => {
+                        return Err(anyhow!(
+                            "Cycle through devices '{}' and '{}'",
+                            self.names[node],
+                            self.names[output]
+                        ))
+                    }
+                    State::Done => {}
+                }
+            }
+        }
+
+        post_order.reverse();
+        Ok(post_order)
+    }
+}
+
+/// Number of paths from every device reachable from `start` to `end`.
+/// Paths stop at `end`; other devices get 0.
+fn paths_to(graph: &Graph, start: NodeId, end: NodeId) -> Result<Vec<usize>> {
+    paths_in_order(graph, &graph.topological_order(start)?, end)
+}
+
+/// Number of paths from each device of `order` to `end`, by dynamic
+/// programming over the devices in reverse topological order.
+fn paths_in_order(graph: &Graph, order: &[NodeId], end: NodeId) -> Result<Vec<usize>> {
+    let mut paths = vec![0usize; graph.names.len()];
+    for &node in order.iter().rev() {
+        paths[node] = if node == end {
+            1
+        } else {
+            graph.outputs[node]
+                .iter()
+                .try_fold(0usize, |sum, &output| sum.checked_add(paths[output]))
+                .ok_or_else(|| anyhow!("Path count overflow at '{}'", graph.names[node]))?
+        };
+    }
+    Ok(paths)
+}
+
+/// Renders the device graph in Graphviz DOT format. Every device is labeled
+/// with the number of paths from the query's source to it and from it to
+/// the sink. Required waypoints are filled, devices the source cannot reach
+/// are grey and dashed, and dead ends that cannot reach the sink are red.
+/// Edges that no source to sink path uses are grey.
+fn graph_to_dot(graph: &Graph, query: &PathQuery) -> Result<String> {
+    let n = graph.names.len();
+    let to_sink = paths_in_order(graph, &graph.topological_order_from(0..n)?, query.sink)?;
+    let mut from_source = vec![0usize; n];
+    from_source[query.source] = 1;
+    for node in graph.topological_order(query.source)? {
+        if node == query.sink {
+            continue;
+        }
+        for &output in &graph.outputs[node] {
+            from_source[output] = from_source[output]
+                .checked_add(from_source[node])
+                .ok_or_else(|| anyhow!("Path count overflow at '{}'", graph.names[output]))?;
+        }
+    }
+
+    let names = graph
+        .names
+        .iter()
+        .map(|name| dot_escape(name))
+        .collect::<Vec<_>>();
+    let source = &names[query.source];
+    let sink = &names[query.sink];
+    let mut ret = String::from("digraph devices {\n    rankdir=LR;\n");
+    for (node, name) in names.iter().enumerate() {
+        let mut attributes = vec![format!(
+            "label=\"{}\\nfrom {}: {}\\nto {}: {}\"",
+            name, source, from_source[node], sink, to_sink[node]
+        )];
+        if node == query.source || node == query.sink {
+            attributes.push("shape=doublecircle".to_string());
+        }
+        // Graphviz keeps only the last `style`, so the styles are combined
+        let mut styles = vec![];
+        if query.required.contains(&node) {
+            attributes.push("fillcolor=gold".to_string());
+            styles.push("filled");
+        }
+        if from_source[node] == 0 {
+            attributes.push("color=grey fontcolor=grey".to_string());
+            styles.push("dashed");
+        } else if to_sink[node] == 0 {
+            attributes.push("color=red fontcolor=red".to_string());
+        }
+        if !styles.is_empty() {
+            attributes.push(format!("style=\"{}\"", styles.join(",")));
+        }
+        ret.push_str(&format!("    \"{}\" [{}];\n", name, attributes.join(" ")));
+    }
+    for (node, outputs) in graph.outputs.iter().enumerate() {
+        for &output in outputs {
+            let used = node != query.sink && from_source[node] > 0 && to_sink[output] > 0;
+            ret.push_str(&format!(
+                "    \"{}\" -> \"{}\"{};\n",
+                names[node],
+                names[output],
+                if used { "" } else { " [color=grey]" }
+            ));
+        }
+    }
+    ret.push_str("}\n");
+    Ok(ret)
+}
+
+/// Escapes `name` for use inside a quoted DOT string.
+fn dot_escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn count_paths(graph: &Graph, start: NodeId, end: NodeId) -> Result<usize> {
+    Ok(paths_to(graph, start, end)?[start])
+}
+
+/// Which of the devices reachable from `start` can reach `end`; other
+/// devices get `false`. Unlike the counts of `paths_to`, this cannot overflow.
+fn reaches(graph: &Graph, start: NodeId, end: NodeId) -> Result<Vec<bool>> {
+    let mut ret = vec![false; graph.names.len()];
+    for node in graph.topological_order(start)?.into_iter().rev() {
+        ret[node] = node == end || graph.outputs[node].iter().any(|&output| ret[output]);
+    }
+    Ok(ret)
+}
+
+/// Lazily enumerates the paths from `start` to `end` in depth-first order,
+/// never descending into devices that cannot reach `end`.
+struct Paths<'a> {
+    graph: &'a Graph,
+    end: NodeId,
+    reaches_end: Vec<bool>,
+    /// Devices of the current path, each with the index of its next output to try
+    stack: Vec<(NodeId, usize)>,
+}
+
+impl<'a> Paths<'a> {
+    fn new(graph: &'a Graph, start: NodeId, end: NodeId) -> Result<Self> {
+        let reaches_end = reaches(graph, start, end)?;
+        let stack = if reaches_end[start] {
+            vec![(start, 0)]
+        } else {
+            vec![]
+        };
+        Ok(Paths {
+            graph,
+            end,
+            reaches_end,
+            stack,
+        })
+    }
+}
+
+impl Iterator for Paths<'_> {
+    type Item = Vec<NodeId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, next)) = self.stack.last_mut() {
+            let node = *node;
+            if node == self.end {
+                let path = self.stack.iter().map(|&(node, _)| node).collect();
+                self.stack.pop();
+                return Some(path);
+            }
+            match self.graph.outputs[node].get(*next) {
+                Some(&output) => {
+                    *next += 1;
+                    if self.reaches_end[output] {
+                        self.stack.push((output, 0));
+                    }
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Small SplitMix64 generator, good enough for sampling.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
 
-fn count_paths2(
-    start: &str,
-    end: &str,
-    graph: &HashMap<String, Vec<String>>,
-    dac_seen: bool,
-    fft_seen: bool,
-    cache: &mut Cache,
-) -> usize {
-    if start == end {
-        return if dac_seen && fft_seen { 1 } else { 0 };
+    /// Number in `0..n`, with a bias of at most `n / 2^64`.
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
     }
-    if let Some(cached) = cache.get(&(start.to_string(), dac_seen, fft_seen)) {
-        return *cached;
+}
+
+/// Draws a path from `start` to `end` uniformly at random, by picking each
+/// output with a probability proportional to the number of paths through it.
+/// `paths` must come from `paths_to` for the same devices.
+fn sample_path(
+    graph: &Graph,
+    paths: &[usize],
+    start: NodeId,
+    end: NodeId,
+    rng: &mut SplitMix64,
+) -> Option<Vec<NodeId>> {
+    if paths[start] == 0 {
+        return None;
     }
+    let mut ret = vec![start];
+    let mut node = start;
+    while node != end {
+        let mut pick = rng.below(paths[node]);
+        for &output in &graph.outputs[node] {
+            if pick < paths[output] {
+                node = output;
+                break;
+            }
+            pick -= paths[output];
+        }
+        ret.push(node);
+    }
+    Some(ret)
+}
 
-    let mut ret = 0;
-    if let Some(neighbors) = graph.get(start) {
-        for neighbor in neighbors {
-            let new_dac_seen = dac_seen || neighbor == "dac";
-            let new_fft_seen = fft_seen || neighbor == "fft";
-            ret += count_paths2(neighbor, end, graph, new_dac_seen, new_fft_seen, cache);
+/// Paths from `source` to `sink` that visit all `required` devices, none of
+/// the `forbidden` ones, and respect the `order` constraints.
+#[derive(Debug, Clone)]
+struct PathQuery {
+    source: NodeId,
+    sink: NodeId,
+    required: Vec<NodeId>,
+    forbidden: Vec<NodeId>,
+    /// `(a, b)`: a path may only visit `b` after it visited `a`
+    order: Vec<(NodeId, NodeId)>,
+}
+
+impl PathQuery {
+    fn new(source: NodeId, sink: NodeId) -> Self {
+        PathQuery {
+            source,
+            sink,
+            required: vec![],
+            forbidden: vec![],
+            order: vec![],
         }
     }
-    cache.insert((start.to_string(), dac_seen, fft_seen), ret);
-    ret
+
+    /// Reads `--from` (default `svr`), `--to` (default `out`), and the comma
+    /// separated lists `--via a,b`, `--avoid c,d` and `--order a:b,c:d`.
+    fn from_cli(graph: &Graph) -> Result<Self> {
+        let list = |name: &str| -> Result<Vec<NodeId>> {
+            match cli_option(name) {
+                Some(value) => value.split(',').map(|device| graph.id(device)).collect(),
+                None => Ok(vec![]),
+            }
+        };
+        let order = match cli_option("order") {
+            Some(value) => value
+                .split(',')
+                .map(|pair| {
+                    let (a, b) = pair
+                        .split_once(':')
+                        .ok_or_else(|| anyhow!("Expected 'a:b' in --order, got '{}'", pair))?;
+                    Ok((graph.id(a)?, graph.id(b)?))
+                })
+                .collect::<Result<_>>()?,
+            None => vec![],
+        };
+
+        Ok(PathQuery {
+            source: graph.id(&cli_value("from", "svr".to_string())?)?,
+            sink: graph.id(&cli_value("to", "out".to_string())?)?,
+            required: list("via")?,
+            forbidden: list("avoid")?,
+            order,
+        })
+    }
+}
+
+/// Per-device constraints of a query, with the tracked devices (required or
+/// ordered) numbered by bit.
+struct QueryMasks {
+    /// Bit of every device, 0 for untracked ones
+    bits: Vec<u64>,
+    /// Devices that must have been visited before entering a device
+    prerequisites: Vec<u64>,
+    forbidden: Vec<bool>,
+    required: u64,
 }
 
-fn count_paths(start: &str, end: &str, graph: &HashMap<String, Vec<String>>) -> usize {
-    let mut ret = 0;
+impl QueryMasks {
+    fn new(graph: &Graph, query: &PathQuery) -> Result<Self> {
+        let n = graph.names.len();
+        let mut bits = vec![0u64; n];
+        let mut next_bit = 0;
+        let ordered = query.order.iter().flat_map(|&(a, b)| [a, b]);
+        for node in query.required.iter().copied().chain(ordered) {
+            if bits[node] == 0 {
+                if next_bit == u64::BITS {
+                    return Err(anyhow!("At most {} waypoints are supported", u64::BITS));
+                }
+                bits[node] = 1 << next_bit;
+                next_bit += 1;
+            }
+        }
+
+        let mut prerequisites = vec![0u64; n];
+        for &(a, b) in &query.order {
+            prerequisites[b] |= bits[a];
+        }
+        let mut forbidden = vec![false; n];
+        for &node in &query.forbidden {
+            forbidden[node] = true;
+        }
+        let required = query
+            .required
+            .iter()
+            .fold(0, |mask, &node| mask | bits[node]);
+
+        Ok(QueryMasks {
+            bits,
+            prerequisites,
+            forbidden,
+            required,
+        })
+    }
+
+    /// Visited devices after entering `node`, or `None` if the query forbids it.
+    fn enter(&self, visited: u64, node: NodeId) -> Option<u64> {
+        let prerequisites = self.prerequisites[node];
+        (!self.forbidden[node] && visited & prerequisites == prerequisites)
+            .then_some(visited | self.bits[node])
+    }
+}
 
-    let mut todo = VecDeque::new();
-    todo.push_back(start.to_string());
+/// Number of paths matching the query, by dynamic programming over the
+/// devices in topological order. Every device keeps the number of ways to
+/// reach it per set of tracked devices visited so far; only the sets that
+/// actually occur are stored.
+fn count_query_paths(graph: &Graph, query: &PathQuery) -> Result<usize> {
+    let masks = QueryMasks::new(graph, query)?;
+    let mut ways: Vec<HashMap<u64, usize>> = vec![HashMap::new(); graph.names.len()];
+    if let Some(visited) = masks.enter(0, query.source) {
+        ways[query.source].insert(visited, 1);
+    }
 
-    while let Some(node) = todo.pop_front() {
-        if node == end {
-            ret += 1;
+    for node in graph.topological_order(query.source)? {
+        if node == query.sink {
             continue;
         }
-        if let Some(neighbors) = graph.get(&node) {
-            for neighbor in neighbors {
-                todo.push_back(neighbor.to_string());
+        let current = std::mem::take(&mut ways[node]);
+        for &output in &graph.outputs[node] {
+            for (&visited, &count) in &current {
+                if let Some(visited) = masks.enter(visited, output) {
+                    let entry = ways[output].entry(visited).or_insert(0);
+                    *entry = entry.checked_add(count).ok_or_else(|| {
+                        anyhow!("Path count overflow at '{}'", graph.names[output])
+                    })?;
+                }
             }
         }
     }
 
-    ret
+    ways[query.sink]
+        .iter()
+        .filter(|&(&visited, _)| visited & masks.required == masks.required)
+        .try_fold(0usize, |sum, (_, &count)| sum.checked_add(count))
+        .ok_or_else(|| anyhow!("Path count overflow at '{}'", graph.names[query.sink]))
 }
 
-fn parse_input<R: BufRead>(reader: R) -> Result<HashMap<String, Vec<String>>> {
-    let mut ret = HashMap::new();
-    for line in reader.lines() {
-        let line = line?;
-        let parts = line
-            .split(' ')
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>();
-        let node = parts[0].trim_end_matches(':').to_string();
-        let neighbors = parts[1..].to_vec();
-        ret.insert(node, neighbors);
+/// Devices that may be referenced without a line of their own.
+const SINKS: &[&str] = &["out"];
+
+/// Parses lines of the form `name: output output ...`, where the outputs
+/// are separated by single spaces and may be missing. Blank lines are
+/// skipped. Every device must be defined once, except for the `SINKS`.
+fn parse_input<R: BufRead>(reader: R) -> Result<Graph> {
+    let mut ret = Graph::default();
+    let mut defined = HashMap::new();
+    let mut referenced = HashMap::new();
+
+    for line in parse::numbered_lines(reader) {
+        let (line_no, line) = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |column, message: String| parse::ParseError::new(line_no, column, message);
+
+        let (name, outputs) = line.split_once(':').ok_or_else(|| {
+            error(
+                1,
+                format!("missing ':' after the device name in '{}'", line),
+            )
+        })?;
+        check_device_name(name, line_no, 1)?;
+        let node = ret.intern(name);
+        if let Some(first) = defined.insert(node, line_no) {
+            let message = format!(
+                "duplicate definition of device '{}' (first defined in line {})",
+                name, first
+            );
+            return Err(error(1, message).into());
+        }
+
+        if outputs.is_empty() {
+            continue;
+        }
+        let colon_column = name.chars().count() + 1;
+        let outputs = outputs
+            .strip_prefix(' ')
+            .ok_or_else(|| error(colon_column + 1, "expected a space after ':'".to_string()))?;
+        for (column, output) in parse::split_columns(outputs, ' ', colon_column + 2) {
+            check_device_name(output, line_no, column)?;
+            let output = ret.intern(output);
+            referenced.entry(output).or_insert((line_no, column));
+            ret.outputs[node].push(output);
+        }
+    }
+
+    let undefined = referenced
+        .iter()
+        .filter(|&(node, _)| {
+            !defined.contains_key(node) && !SINKS.contains(&ret.names[*node].as_str())
+        })
+        .min_by_key(|&(&node, &position)| (position, node));
+    if let Some((&node, &(line_no, column))) = undefined {
+        let message = format!("device '{}' is never defined", ret.names[node]);
+        return Err(parse::ParseError::new(line_no, column, message).into());
     }
 
     Ok(ret)
 }
+
+fn check_device_name(name: &str, line_no: usize, column: usize) -> Result<(), parse::ParseError> {
+    if name.is_empty() {
+        return Err(parse::ParseError::new(line_no, column, "empty device name"));
+    }
+    if name.contains(|c: char| c.is_whitespace() || c == ':') {
+        let message = format!("invalid device name '{}'", name);
+        return Err(parse::ParseError::new(line_no, column, message));
+    }
+    Result::Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_paths_long_chain() -> Result<()> {
+        // Deep enough to overflow the stack with a recursive walk, with 2^60 paths
+        let mut input = String::new();
+        for i in 0..200_000 {
+            input.push_str(&format!("n{}: n{}\n", i, i + 1));
+        }
+        input.push_str("n200000: out\n");
+        let graph = parse_input(input.as_bytes())?;
+        assert_eq!(count_paths(&graph, graph.id("n0")?, graph.id("out")?)?, 1);
+
+        let mut diamonds = String::new();
+        for i in 0..60 {
+            diamonds.push_str(&format!(
+                "d{i}: l{i} r{i}\nl{i}: d{}\nr{i}: d{}\n",
+                i + 1,
+                i + 1
+            ));
+        }
+        diamonds.push_str("d60: out\n");
+        let graph = parse_input(diamonds.as_bytes())?;
+        assert_eq!(
+            count_paths(&graph, graph.id("d0")?, graph.id("out")?)?,
+            1 << 60
+        );
+
+        // The 2^64 paths from d1 already do not fit
+        let mut diamonds = String::new();
+        for i in 0..65 {
+            diamonds.push_str(&format!(
+                "d{i}: l{i} r{i}\nl{i}: d{}\nr{i}: d{}\n",
+                i + 1,
+                i + 1
+            ));
+        }
+        diamonds.push_str("d65: out\n");
+        let graph = parse_input(diamonds.as_bytes())?;
+        let error = count_paths(&graph, graph.id("d0")?, graph.id("out")?).unwrap_err();
+        assert_eq!(error.to_string(), "Path count overflow at 'd1'");
+        // Enumerating needs no counts, so it still works
+        let names = |path: Vec<NodeId>| path.iter().map(|&node| &graph.names[node]).join(",");
+        let first = Paths::new(&graph, graph.id("d0")?, graph.id("out")?)?
+            .take(3)
+            .map(names)
+            .collect::<Vec<_>>();
+        let lefts = |n| (0..n).map(|i| format!("d{i},l{i}")).join(",");
+        assert_eq!(first[0], format!("{},d65,out", lefts(65)));
+        assert_eq!(first[1], format!("{},d64,r64,d65,out", lefts(64)));
+        assert_eq!(first.len(), 3);
+        let query = PathQuery::new(graph.id("d0")?, graph.id("out")?);
+        assert!(graph_to_dot(&graph, &query).is_err());
+
+        // The order on c, which no path visits, tracks a and b, so the 2^63 paths
+        // through each are only added up at the sink
+        let mut split = String::from("src: a b\na: d0\nb: d0\nc: out\n");
+        for i in 0..63 {
+            split.push_str(&format!(
+                "d{i}: l{i} r{i}\nl{i}: d{}\nr{i}: d{}\n",
+                i + 1,
+                i + 1
+            ));
+        }
+        split.push_str("d63: out\n");
+        let graph = parse_input(split.as_bytes())?;
+        let query = PathQuery {
+            order: vec![
+                (graph.id("a")?, graph.id("c")?),
+                (graph.id("b")?, graph.id("c")?),
+            ],
+            ..PathQuery::new(graph.id("src")?, graph.id("out")?)
+        };
+        let error = count_query_paths(&graph, &query).unwrap_err();
+        assert_eq!(error.to_string(), "Path count overflow at 'out'");
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_queries() -> Result<()> {
+        let graph = parse_input(TEST2.as_bytes())?;
+        let id = |name| graph.id(name).unwrap();
+        let base = PathQuery::new(id("svr"), id("out"));
+        let count = |query: PathQuery| count_query_paths(&graph, &query).unwrap();
+
+        assert_eq!(count(base.clone()), 8);
+        assert_eq!(
+            count(PathQuery {
+                required: vec![id("dac")],
+                ..base.clone()
+            }),
+            4
+        );
+        assert_eq!(
+            count(PathQuery {
+                forbidden: vec![id("hub")],
+                ..base.clone()
+            }),
+            4
+        );
+        assert_eq!(
+            count(PathQuery {
+                required: vec![id("dac"), id("tty")],
+                ..base.clone()
+            }),
+            2
+        );
+        assert_eq!(
+            count(PathQuery {
+                order: vec![(id("fft"), id("dac"))],
+                ..base.clone()
+            }),
+            6
+        );
+        assert_eq!(
+            count(PathQuery {
+                required: vec![id("dac"), id("fft")],
+                order: vec![(id("dac"), id("fft"))],
+                ..base.clone()
+            }),
+            0
+        );
+        assert_eq!(
+            count(PathQuery {
+                forbidden: vec![id("svr")],
+                ..base.clone()
+            }),
+            0
+        );
+        assert_eq!(count(PathQuery::new(id("fft"), id("ddd"))), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_enumerate_and_sample() -> Result<()> {
+        let graph = parse_input(TEST.as_bytes())?;
+        let (start, end) = (graph.id("you")?, graph.id("out")?);
+        let names = |path: &[NodeId]| {
+            path.iter()
+                .map(|&node| graph.names[node].as_str())
+                .join(",")
+        };
+
+        let all = Paths::new(&graph, start, end)?
+            .map(|p| names(&p))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            all,
+            [
+                "you,bbb,ddd,ggg,out",
+                "you,bbb,eee,out",
+                "you,ccc,ddd,ggg,out",
+                "you,ccc,eee,out",
+                "you,ccc,fff,out",
+            ]
+        );
+        assert_eq!(Paths::new(&graph, start, end)?.take(2).count(), 2);
+        assert_eq!(
+            Paths::new(&graph, graph.id("ggg")?, graph.id("aaa")?)?.count(),
+            0
+        );
+
+        let paths = paths_to(&graph, start, end)?;
+        let mut rng = SplitMix64(7);
+        let mut seen = HashMap::new();
+        for _ in 0..5000 {
+            let path = sample_path(&graph, &paths, start, end, &mut rng).unwrap();
+            *seen.entry(names(&path)).or_insert(0) += 1;
+        }
+        assert_eq!(seen.len(), 5);
+        assert!(seen.values().all(|&n| (850..1150).contains(&n)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph_to_dot() -> Result<()> {
+        let graph = parse_input("you: aaa bbb\naaa: out\nbbb: ccc\nccc:\nddd: aaa\n".as_bytes())?;
+        let query = PathQuery {
+            required: vec![graph.id("aaa")?, graph.id("ddd")?],
+            ..PathQuery::new(graph.id("you")?, graph.id("out")?)
+        };
+        let dot = graph_to_dot(&graph, &query)?;
+        let lines = dot.lines().collect::<Vec<_>>();
+
+        assert!(lines.contains(
+            &r#"    "aaa" [label="aaa\nfrom you: 1\nto out: 1" fillcolor=gold style="filled"];"#
+        ));
+        assert!(lines.contains(
+            &r#"    "bbb" [label="bbb\nfrom you: 1\nto out: 0" color=red fontcolor=red];"#
+        ));
+        assert!(lines.contains(
+            &r#"    "ccc" [label="ccc\nfrom you: 1\nto out: 0" color=red fontcolor=red];"#
+        ));
+        // An unreachable waypoint keeps its highlight
+        assert!(lines.contains(
+            &r#"    "ddd" [label="ddd\nfrom you: 0\nto out: 1" fillcolor=gold color=grey fontcolor=grey style="filled,dashed"];"#
+        ));
+        assert!(lines.contains(&r#"    "you" -> "aaa";"#));
+        assert!(lines.contains(&r#"    "you" -> "bbb" [color=grey];"#));
+        assert!(lines.contains(&r#"    "ddd" -> "aaa" [color=grey];"#));
+
+        let graph = parse_input("you: a\"b\\c\na\"b\\c: out\n".as_bytes())?;
+        let dot = graph_to_dot(&graph, &PathQuery::new(graph.id("you")?, graph.id("out")?))?;
+        let lines = dot.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&r#"    "a\"b\\c" [label="a\"b\\c\nfrom you: 1\nto out: 1"];"#));
+        assert!(lines.contains(&r#"    "you" -> "a\"b\\c";"#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| parse_input(input.as_bytes()).unwrap_err().to_string();
+
+        assert_eq!(
+            error("aaa: out\nbbb out\n"),
+            "Line 2, column 1: missing ':' after the device name in 'bbb out'"
+        );
+        assert_eq!(
+            error("aaa: bbb\nbbb: out\n\naaa: out\n"),
+            "Line 4, column 1: duplicate definition of device 'aaa' (first defined in line 1)"
+        );
+        assert_eq!(
+            error("aaa: bbb  out\nbbb:\n"),
+            "Line 1, column 10: empty device name"
+        );
+        assert_eq!(error("aaa: out \n"), "Line 1, column 10: empty device name");
+        assert_eq!(error(": out\n"), "Line 1, column 1: empty device name");
+        assert_eq!(
+            error("aaa:out\n"),
+            "Line 1, column 5: expected a space after ':'"
+        );
+        assert_eq!(
+            error("a a: out\n"),
+            "Line 1, column 1: invalid device name 'a a'"
+        );
+        assert_eq!(
+            error("aaa: bbb\nbbb: ccc ddd\nddd: out\n"),
+            "Line 2, column 6: device 'ccc' is never defined"
+        );
+        assert!(parse_input("aaa: bbb\nbbb:\n\nccc: out\n".as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_cycle() -> Result<()> {
+        let graph = parse_input("you: aaa\naaa: bbb\nbbb: aaa out\n".as_bytes())?;
+        let error = count_paths(&graph, graph.id("you")?, graph.id("out")?).unwrap_err();
+        assert_eq!(error.to_string(), "Cycle through devices 'bbb' and 'aaa'");
+        Ok(())
+    }
+}
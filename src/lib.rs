@@ -1,9 +1,66 @@
+pub mod parallel;
+pub mod parse;
+pub mod search;
+
 pub fn start_day(day: &str) {
     println!("Advent of Code 2025 - Day {:0>2}", day);
 }
 
 // Additional common functions
 
+/// Returns true if `--<name>` was passed on the command line.
+pub fn cli_flag(name: &str) -> bool {
+    let flag = format!("--{}", name);
+    std::env::args().skip(1).any(|arg| arg == flag)
+}
+
+/// Returns the value of `--<name> <value>` or `--<name>=<value>` from the command line,
+/// or `None` if the option is absent or has no value.
+pub fn cli_option(name: &str) -> Option<String> {
+    find_option(std::env::args().skip(1), name).flatten()
+}
+
+/// Parses the value of `--<name>`, falling back to `default` if it is absent.
+/// An option without a value is an error.
+pub fn cli_value<T>(name: &str, default: T) -> anyhow::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    option_value(std::env::args().skip(1), name, default)
+}
+
+fn option_value<I, T>(args: I, name: &str, default: T) -> anyhow::Result<T>
+where
+    I: Iterator<Item = String>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match find_option(args, name) {
+        Some(Some(value)) => value
+            .parse()
+            .map_err(|err| anyhow::anyhow!("Invalid value '{}' for --{}: {}", value, name, err)),
+        Some(None) => Err(anyhow::anyhow!("Missing value for --{}", name)),
+        None => Ok(default),
+    }
+}
+
+/// `None` if `--<name>` is absent, `Some(None)` if it is not followed by a
+/// value. A following `--option` does not count as a value.
+fn find_option<I: Iterator<Item = String>>(mut args: I, name: &str) -> Option<Option<String>> {
+    let flag = format!("--{}", name);
+    let prefix = format!("{}=", flag);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return Some(args.next().filter(|value| !value.starts_with("--")));
+        }
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(Some(value.to_string()));
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -12,4 +69,38 @@ mod tests {
     fn it_works() {
         start_day("00");
     }
+
+    #[test]
+    fn test_find_option() {
+        let args = |s: &str| {
+            s.split(' ')
+                .map(String::from)
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+        assert_eq!(
+            find_option(args("--n 10 --top 3"), "n"),
+            Some(Some("10".to_string()))
+        );
+        assert_eq!(
+            find_option(args("--n 10 --top=3"), "top"),
+            Some(Some("3".to_string()))
+        );
+        assert_eq!(find_option(args("--n"), "n"), Some(None));
+        assert_eq!(find_option(args("--n --top 3"), "n"), Some(None));
+        assert_eq!(find_option(args("--report"), "n"), None);
+
+        assert_eq!(option_value(args("--n 10"), "n", 1).unwrap(), 10);
+        assert_eq!(option_value(args("--report"), "n", 1).unwrap(), 1);
+        assert_eq!(
+            option_value(args("--connections"), "connections", 1000)
+                .unwrap_err()
+                .to_string(),
+            "Missing value for --connections"
+        );
+        assert_eq!(
+            option_value(args("--n x"), "n", 1).unwrap_err().to_string(),
+            "Invalid value 'x' for --n: invalid digit found in string"
+        );
+    }
 }
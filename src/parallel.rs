@@ -0,0 +1,53 @@
+//! Optional parallelism for puzzles made of independent units. With the
+//! `parallel` feature the units run on a rayon thread pool; without it they
+//! are processed sequentially.
+
+/// Sets up the thread pool with `--threads N` threads (default: one per
+/// CPU). Does nothing without the `parallel` feature.
+pub fn init_thread_pool() -> anyhow::Result<()> {
+    let threads: usize = crate::cli_value("threads", 0)?;
+    #[cfg(feature = "parallel")]
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()?;
+    #[cfg(not(feature = "parallel"))]
+    let _ = threads;
+    Ok(())
+}
+
+/// Applies `f` to every unit and returns the results in order.
+#[cfg(feature = "parallel")]
+pub fn map_units<T, U, F>(units: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    use rayon::prelude::*;
+    units.par_iter().map(f).collect()
+}
+
+/// Applies `f` to every unit and returns the results in order.
+#[cfg(not(feature = "parallel"))]
+pub fn map_units<T, U, F>(units: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    units.iter().map(f).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_units_keeps_order() {
+        let units = (0..1000).collect::<Vec<u64>>();
+        assert_eq!(
+            map_units(&units, |&x| x * x),
+            units.iter().map(|&x| x * x).collect::<Vec<_>>()
+        );
+    }
+}
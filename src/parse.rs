@@ -0,0 +1,305 @@
+//! Line-oriented parsing helpers shared by the days. Errors carry the 1-based
+//! line and column of the offending input.
+
+use std::fmt::{self, Display};
+use std::io::{self, BufRead, Lines};
+use std::iter::Enumerate;
+use std::str::FromStr;
+
+/// Error at a position of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Lines of a reader together with their 1-based line numbers.
+pub struct NumberedLines<R> {
+    lines: Enumerate<Lines<R>>,
+}
+
+impl<R: BufRead> Iterator for NumberedLines<R> {
+    type Item = io::Result<(usize, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (idx, line) = self.lines.next()?;
+        Some(line.map(|line| (idx + 1, line)))
+    }
+}
+
+pub fn numbered_lines<R: BufRead>(reader: R) -> NumberedLines<R> {
+    NumberedLines {
+        lines: reader.lines().enumerate(),
+    }
+}
+
+/// Reads lines up to and including the next blank line, or to the end of
+/// the input, and returns the ones before it. The remaining lines are left
+/// in `lines`, so the following section can be streamed.
+pub fn next_section<I>(lines: &mut I) -> io::Result<Vec<(usize, String)>>
+where
+    I: Iterator<Item = io::Result<(usize, String)>>,
+{
+    let mut ret = Vec::new();
+    for line in lines {
+        let (line_no, line) = line?;
+        if line.trim().is_empty() {
+            break;
+        }
+        ret.push((line_no, line));
+    }
+    Ok(ret)
+}
+
+/// All sections of the input separated by blank lines. Runs of blank lines
+/// count as a single separator.
+pub fn sections<R: BufRead>(reader: R) -> io::Result<Vec<Vec<(usize, String)>>> {
+    let mut lines = numbered_lines(reader).peekable();
+    let mut ret = Vec::new();
+    while lines.peek().is_some() {
+        let section = next_section(&mut lines)?;
+        if !section.is_empty() {
+            ret.push(section);
+        }
+    }
+    Ok(ret)
+}
+
+/// Pieces of `text` between the separators, each with its column. `column`
+/// is the column of the first character of `text`.
+pub fn split_columns(
+    text: &str,
+    separator: char,
+    column: usize,
+) -> impl Iterator<Item = (usize, &str)> {
+    let mut next_column = column;
+    text.split(separator).map(move |piece| {
+        let piece_column = next_column;
+        next_column += piece.chars().count() + 1;
+        (piece_column, piece)
+    })
+}
+
+/// Parses a value surrounded by optional whitespace, starting at `column`.
+pub fn parse_value<T>(text: &str, line: usize, column: usize) -> Result<T, ParseError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let trimmed = text.trim_start();
+    let column = column + text.len() - trimmed.len();
+    let trimmed = trimmed.trim_end();
+    trimmed.parse().map_err(|err| {
+        ParseError::new(
+            line,
+            column,
+            format!("invalid value '{}': {}", trimmed, err),
+        )
+    })
+}
+
+/// Parses the values between the separators, e.g. `1,2,3`.
+pub fn parse_list<T>(
+    text: &str,
+    separator: char,
+    line: usize,
+    column: usize,
+) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    split_columns(text, separator, column)
+        .map(|(column, piece)| parse_value(piece, line, column))
+        .collect()
+}
+
+/// Parses a whole line of exactly `N` comma-separated values, e.g. `3,4,5`.
+pub fn parse_csv_line<T, const N: usize>(text: &str, line: usize) -> Result<[T; N], ParseError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    parse_list(text, ',', line, 1)?
+        .try_into()
+        .map_err(|values: Vec<T>| {
+            let message = format!(
+                "expected {} comma-separated values, got {}",
+                N,
+                values.len()
+            );
+            ParseError::new(line, 1, message)
+        })
+}
+
+/// Parses an inclusive range `a-b` with `a <= b`.
+pub fn parse_range<T>(text: &str, line: usize, column: usize) -> Result<(T, T), ParseError>
+where
+    T: FromStr + PartialOrd,
+    T::Err: Display,
+{
+    let (start, end) = text.split_once('-').ok_or_else(|| {
+        ParseError::new(
+            line,
+            column,
+            format!("expected a range 'a-b', got '{}'", text),
+        )
+    })?;
+    let start_value = parse_value(start, line, column)?;
+    let end_value = parse_value(end, line, column + start.chars().count() + 1)?;
+    if start_value > end_value {
+        return Err(ParseError::new(
+            line,
+            column,
+            format!("reversed range '{}'", text),
+        ));
+    }
+    Ok((start_value, end_value))
+}
+
+/// Decimal digits of a line, e.g. `123` gives `[1, 2, 3]`.
+pub fn digits(text: &str, line: usize) -> Result<Vec<u8>, ParseError> {
+    text.chars()
+        .enumerate()
+        .map(|(x, ch)| {
+            ch.to_digit(10)
+                .map(|d| d as u8)
+                .ok_or_else(|| ParseError::new(line, x + 1, format!("invalid digit '{}'", ch)))
+        })
+        .collect()
+}
+
+/// Rectangular grid of cells, indexed by `[y][x]`. `cell` converts a
+/// character, returning `None` for invalid ones.
+pub fn char_grid<R: BufRead, T>(
+    reader: R,
+    mut cell: impl FnMut(char) -> Option<T>,
+) -> anyhow::Result<Vec<Vec<T>>> {
+    let mut grid: Vec<Vec<T>> = Vec::new();
+    for line in numbered_lines(reader) {
+        let (line_no, line) = line?;
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(x, ch)| {
+                cell(ch).ok_or_else(|| {
+                    ParseError::new(line_no, x + 1, format!("invalid character '{}'", ch))
+                })
+            })
+            .collect::<Result<Vec<T>, _>>()?;
+        if let Some(first) = grid.first() {
+            if row.len() != first.len() {
+                let message = format!("expected {} columns, got {}", first.len(), row.len());
+                let column = row.len().min(first.len()) + 1;
+                return Err(ParseError::new(line_no, column, message).into());
+            }
+        }
+        grid.push(row);
+    }
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_and_lists() {
+        assert_eq!(parse_csv_line::<i64, 3>("1,-2, 3", 1), Ok([1, -2, 3]));
+        assert_eq!(
+            parse_csv_line::<i64, 3>("1,2", 4).unwrap_err().to_string(),
+            "Line 4, column 1: expected 3 comma-separated values, got 2"
+        );
+        assert_eq!(
+            parse_csv_line::<i64, 2>("10, x", 2)
+                .unwrap_err()
+                .to_string(),
+            "Line 2, column 5: invalid value 'x': invalid digit found in string"
+        );
+        assert_eq!(
+            parse_list::<u8>("(1,3)".trim_matches(['(', ')']), ',', 1, 2),
+            Ok(vec![1, 3])
+        );
+        assert_eq!(
+            split_columns("ab  c", ' ', 3).collect::<Vec<_>>(),
+            [(3, "ab"), (6, ""), (7, "c")]
+        );
+    }
+
+    #[test]
+    fn test_ranges_and_digits() {
+        assert_eq!(parse_range::<u64>("3-5", 1, 1), Ok((3, 5)));
+        assert_eq!(
+            parse_range::<u64>("14-10", 2, 7).unwrap_err().to_string(),
+            "Line 2, column 7: reversed range '14-10'"
+        );
+        assert_eq!(
+            parse_range::<u64>("7", 2, 1).unwrap_err().to_string(),
+            "Line 2, column 1: expected a range 'a-b', got '7'"
+        );
+        assert_eq!(parse_range::<u64>("3-x", 1, 5).unwrap_err().column, 7);
+
+        assert_eq!(digits("907", 1), Ok(vec![9, 0, 7]));
+        assert_eq!(
+            digits("9a7", 3).unwrap_err().to_string(),
+            "Line 3, column 2: invalid digit 'a'"
+        );
+    }
+
+    #[test]
+    fn test_grids_and_sections() -> anyhow::Result<()> {
+        let cell = |ch| (ch == '#' || ch == '.').then_some(ch == '#');
+        assert_eq!(
+            char_grid("#.\n.#\n".as_bytes(), cell)?,
+            [[true, false], [false, true]]
+        );
+        assert_eq!(
+            char_grid("#.\n.\n".as_bytes(), cell)
+                .unwrap_err()
+                .to_string(),
+            "Line 2, column 2: expected 2 columns, got 1"
+        );
+        assert_eq!(
+            char_grid("#.\n.x\n".as_bytes(), cell)
+                .unwrap_err()
+                .to_string(),
+            "Line 2, column 2: invalid character 'x'"
+        );
+
+        let parsed = sections("a\nb\n\n\nc\n\n".as_bytes())?;
+        let lines =
+            |section: &[(usize, String)]| section.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+        assert_eq!(
+            parsed.iter().map(|s| lines(s)).collect::<Vec<_>>(),
+            [vec![1, 2], vec![5]]
+        );
+
+        let mut numbered = numbered_lines("a\n\nb\nc\n".as_bytes());
+        assert_eq!(next_section(&mut numbered)?, [(1, "a".to_string())]);
+        assert_eq!(numbered.next().transpose()?, Some((3, "b".to_string())));
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! Generic graph searches over implicit state spaces. States only need to be
+//! hashable; the caller describes the graph with a neighbor function.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// Breadth-first search from `start`, returning the number of steps to the
+/// nearest state for which `is_goal` holds. States are marked as visited
+/// when they are queued, so every state is expanded at most once.
+pub fn bfs<S, I>(
+    start: S,
+    mut neighbors: impl FnMut(&S) -> I,
+    mut is_goal: impl FnMut(&S) -> bool,
+) -> Option<usize>
+where
+    S: Clone + Eq + Hash,
+    I: IntoIterator<Item = S>,
+{
+    let mut visited = HashSet::from([start.clone()]);
+    let mut todo = VecDeque::from([(start, 0)]);
+
+    while let Some((state, steps)) = todo.pop_front() {
+        if is_goal(&state) {
+            return Some(steps);
+        }
+        for next in neighbors(&state) {
+            if visited.insert(next.clone()) {
+                todo.push_back((next, steps + 1));
+            }
+        }
+    }
+
+    None
+}
+
+/// Dijkstra's algorithm from `start`, returning the cost of the cheapest
+/// path to a state for which `is_goal` holds. `neighbors` yields the next
+/// states with the cost of the step there, which must not be negative.
+pub fn dijkstra<S, C, I>(
+    start: S,
+    neighbors: impl FnMut(&S) -> I,
+    is_goal: impl FnMut(&S) -> bool,
+) -> Option<C>
+where
+    S: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    I: IntoIterator<Item = (S, C)>,
+{
+    astar(start, neighbors, |_| C::default(), is_goal)
+}
+
+/// A* search from `start`, returning the cost of the cheapest path to a state
+/// for which `is_goal` holds. `heuristic` must never overestimate the
+/// remaining cost, or the result may not be the cheapest.
+pub fn astar<S, C, I>(
+    start: S,
+    mut neighbors: impl FnMut(&S) -> I,
+    mut heuristic: impl FnMut(&S) -> C,
+    mut is_goal: impl FnMut(&S) -> bool,
+) -> Option<C>
+where
+    S: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    I: IntoIterator<Item = (S, C)>,
+{
+    // States are stored once and referred to by index, so they need not be `Ord`
+    let mut states = vec![start.clone()];
+    let mut index = HashMap::from([(start, 0)]);
+    let mut best = vec![C::default()];
+    let mut todo = BinaryHeap::from([Reverse((heuristic(&states[0]), C::default(), 0))]);
+
+    while let Some(Reverse((_, cost, i))) = todo.pop() {
+        if cost > best[i] {
+            continue;
+        }
+        let state = states[i].clone();
+        if is_goal(&state) {
+            return Some(cost);
+        }
+        for (next, step) in neighbors(&state) {
+            let next_cost = cost + step;
+            let j = match index.entry(next) {
+                Entry::Occupied(entry) => {
+                    let j = *entry.get();
+                    if next_cost >= best[j] {
+                        continue;
+                    }
+                    best[j] = next_cost;
+                    j
+                }
+                Entry::Vacant(entry) => {
+                    let j = states.len();
+                    states.push(entry.key().clone());
+                    entry.insert(j);
+                    best.push(next_cost);
+                    j
+                }
+            };
+            todo.push(Reverse((next_cost + heuristic(&states[j]), next_cost, j)));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: [&str; 3] = ["S9..G", ".###.", "....."];
+
+    /// Moves on `GRID`, costing 1 plus the digit of the cell entered.
+    fn moves((x, y): (i32, i32)) -> Vec<((i32, i32), u32)> {
+        [(0, 1), (1, 0), (0, -1), (-1, 0)]
+            .into_iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter_map(|(x, y)| {
+                let cell = *GRID.get(y as usize)?.as_bytes().get(x as usize)?;
+                (cell != b'#').then(|| ((x, y), 1 + (cell as char).to_digit(10).unwrap_or(0)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_searches() {
+        let goal = |&pos: &(i32, i32)| pos == (4, 0);
+        let steps = |pos| moves(pos).into_iter().map(|(next, _)| next);
+        assert_eq!(bfs((0, 0), |&pos| steps(pos), goal), Some(4));
+        assert_eq!(bfs((0, 0), |&pos| steps(pos), |&pos| pos == (2, 1)), None);
+
+        // The short route through the 9 costs 13, the long one around the wall 8
+        assert_eq!(dijkstra((0, 0), |&pos| moves(pos), goal), Some(8));
+        let manhattan = |&(x, y): &(i32, i32)| (4 - x + y) as u32;
+        assert_eq!(astar((0, 0), |&pos| moves(pos), manhattan, goal), Some(8));
+        assert_eq!(dijkstra((0, 0), |&pos| moves(pos), |_| true), Some(0));
+    }
+}